@@ -0,0 +1,112 @@
+use crate::puzzles::puzzle4::pattern::Pattern;
+use crate::puzzles::puzzle5::live::RuleEdit;
+use crate::puzzles::puzzle6::render::Style;
+use crate::puzzles::puzzle6::TurnPolicy;
+use crate::puzzles::puzzle7::EvaluationMode;
+use core::time::Duration;
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+#[allow(clippy::struct_excessive_bools)] // one per command line switch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub day4_words: Vec<String>,
+    pub day4_patterns: Vec<Pattern>,
+    pub day5_explain: bool,
+    pub day5_analyze: bool,
+    pub day5_dot: Option<PathBuf>,
+    pub day5_rank: bool,
+    pub day5_edits: Vec<RuleEdit>,
+    pub day6_threads: Option<usize>,
+    pub day6_sequential: bool,
+    pub day6_jump: bool,
+    pub day6_sparse: bool,
+    pub day6_bench: Option<usize>,
+    pub day6_turn_policy: TurnPolicy,
+    pub day6_path: bool,
+    pub day6_style: Style,
+    pub day6_render: bool,
+    pub day6_animate: Option<Duration>,
+    pub day6_frames: Option<PathBuf>,
+    pub day6_frame_every: Option<usize>,
+    pub day6_loops: bool,
+    pub day6_loops_render: bool,
+    pub day6_obstacle: Option<(usize, usize)>,
+    pub day6_guards: Option<PathBuf>,
+    pub day6_heatmap: bool,
+    pub day6_heatmap_ppm: Option<PathBuf>,
+    pub day7_mode: EvaluationMode,
+    pub day7_print_solutions: bool,
+}
+
+impl Options {
+    #[inline]
+    pub fn from_env() -> Self {
+        Self::parse(env::args().skip(1)).unwrap_or_else(
+            #[cold]
+            |err| {
+                eprintln!("{}", err);
+                process::exit(2)
+            },
+        )
+    }
+
+    #[inline]
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        for arg in args {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            match (flag, value) {
+                ("--day4-find", Some(word)) => options.day4_words.push(word.to_owned()),
+                // rows separated by `/`, e.g. M.S/.A./M.S
+                ("--day4-pattern", Some(pattern)) => options.day4_patterns.push(pattern.replace('/', "\n").parse()?),
+                ("--day5-explain", None) => options.day5_explain = true,
+                ("--day5-analyze", None) => options.day5_analyze = true,
+                ("--day5-dot", Some(path)) => options.day5_dot = Some(PathBuf::from(path)),
+                ("--day5-rank", None) => options.day5_rank = true,
+                ("--day5-edit", Some(edit)) => options.day5_edits.push(edit.parse()?),
+                ("--day6-threads", Some(threads)) => {
+                    options.day6_threads = Some(threads.parse().map_err(|_| format!("Invalid thread count: {}", threads))?);
+                }
+                ("--day6-sequential", None) => options.day6_sequential = true,
+                ("--day6-jump", None) => options.day6_jump = true,
+                ("--day6-sparse", None) => options.day6_sparse = true,
+                ("--day6-bench", None) => options.day6_bench = Some(10),
+                ("--day6-bench", Some(runs)) => {
+                    let runs = runs.parse().ok().filter(|&runs| runs > 0);
+                    options.day6_bench = Some(runs.ok_or_else(|| format!("Invalid benchmark run count: {}", arg))?);
+                }
+                ("--day6-turn", Some(policy)) => options.day6_turn_policy = policy.parse()?,
+                ("--day6-path", None) => options.day6_path = true,
+                ("--day6-style", Some(style)) => options.day6_style = style.parse()?,
+                ("--day6-render", None) => options.day6_render = true,
+                ("--day6-animate", Some(delay)) => {
+                    let millis = delay.parse().map_err(|_| format!("Invalid frame delay in milliseconds: {}", delay))?;
+                    options.day6_animate = Some(Duration::from_millis(millis));
+                }
+                ("--day6-loops", None) => options.day6_loops = true,
+                ("--day6-loops-render", None) => options.day6_loops_render = true,
+                ("--day6-obstacle", Some(pos)) => {
+                    let parsed = pos.split_once(',').and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)));
+                    options.day6_obstacle = Some(parsed.ok_or_else(|| format!("Invalid obstacle position, expected x,y: {}", pos))?);
+                }
+                ("--day6-heatmap", None) => options.day6_heatmap = true,
+                ("--day6-heatmap-ppm", Some(dir)) => options.day6_heatmap_ppm = Some(PathBuf::from(dir)),
+                ("--day6-guards", Some(path)) => options.day6_guards = Some(PathBuf::from(path)),
+                ("--day6-frames", Some(dir)) => options.day6_frames = Some(PathBuf::from(dir)),
+                ("--day6-frame-every", Some(every)) => {
+                    let every = every.parse().ok().filter(|&every| every > 0);
+                    options.day6_frame_every = Some(every.ok_or_else(|| format!("Invalid frame step count: {}", arg))?);
+                }
+                ("--day7-mode", Some(mode)) => options.day7_mode = mode.parse()?,
+                ("--day7-print-solutions", None) => options.day7_print_solutions = true,
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
@@ -0,0 +1,356 @@
+use core::fmt;
+use core::ops::{Index, IndexMut};
+use core::slice;
+
+/// One of the eight directions on a grid whose `y` grows downwards.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Direction {
+    // the cardinal directions come first, so `dir as usize` can index arrays of four
+    Up,
+    Right,
+    Down,
+    Left,
+    UpRight,
+    DownRight,
+    DownLeft,
+    UpLeft,
+}
+
+impl Direction {
+    pub const CARDINALS: [Self; 4] = [Self::Up, Self::Right, Self::Down, Self::Left];
+    pub const ALL: [Self; 8] = [
+        Self::Up,
+        Self::Right,
+        Self::Down,
+        Self::Left,
+        Self::UpRight,
+        Self::DownRight,
+        Self::DownLeft,
+        Self::UpLeft,
+    ];
+
+    /// Change in `x` and `y` of a single step.
+    #[inline]
+    pub const fn delta(self) -> (isize, isize) {
+        match self {
+            Self::Up => (0, -1),
+            Self::Right => (1, 0),
+            Self::Down => (0, 1),
+            Self::Left => (-1, 0),
+            Self::UpRight => (1, -1),
+            Self::DownRight => (1, 1),
+            Self::DownLeft => (-1, 1),
+            Self::UpLeft => (-1, -1),
+        }
+    }
+
+    #[inline]
+    pub fn offset(self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.offset_times(x, y, 1)
+    }
+
+    #[inline]
+    pub fn offset_bounded(self, x: usize, y: usize, bound_x: usize, bound_y: usize) -> Option<(usize, usize)> {
+        self.offset(x, y).filter(|&(x, y)| x < bound_x && y < bound_y)
+    }
+
+    #[inline]
+    pub fn offset_times(self, x: usize, y: usize, factor: usize) -> Option<(usize, usize)> {
+        let shift = |v: usize, d: isize| match d {
+            ..=-1 => v.checked_sub(factor),
+            0 => Some(v),
+            1.. => v.checked_add(factor),
+        };
+        let (dx, dy) = self.delta();
+        Some((shift(x, dx)?, shift(y, dy)?))
+    }
+
+    /// How many steps `to` lies ahead of `from` when walking this way, `None` if it isn't ahead at all.
+    #[inline]
+    pub fn distance_to(self, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        let steps = if self.delta().0 == 0 {
+            from.1.abs_diff(to.1)
+        } else {
+            from.0.abs_diff(to.0)
+        };
+        (steps > 0 && self.offset_times(from.0, from.1, steps) == Some(to)).then_some(steps)
+    }
+
+    #[inline]
+    pub const fn from_arrow(byte: u8) -> Option<Self> {
+        match byte {
+            b'^' => Some(Self::Up),
+            b'>' => Some(Self::Right),
+            b'v' => Some(Self::Down),
+            b'<' => Some(Self::Left),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub const fn arrow(self) -> char {
+        match self {
+            Self::Up => '^',
+            Self::Right => '>',
+            Self::Down => 'v',
+            Self::Left => '<',
+            Self::UpRight => '↗',
+            Self::DownRight => '↘',
+            Self::DownLeft => '↙',
+            Self::UpLeft => '↖',
+        }
+    }
+
+    #[inline]
+    pub const fn reversed(self) -> Self {
+        self.rotate_clockwise().rotate_clockwise()
+    }
+
+    /// Quarter turn, diagonals stay diagonal.
+    #[inline]
+    pub const fn rotate_clockwise(self) -> Self {
+        match self {
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+            Self::Left => Self::Up,
+            Self::UpRight => Self::DownRight,
+            Self::DownRight => Self::DownLeft,
+            Self::DownLeft => Self::UpLeft,
+            Self::UpLeft => Self::UpRight,
+        }
+    }
+
+    #[inline]
+    pub const fn rotate_counter_clockwise(self) -> Self {
+        self.reversed().rotate_clockwise()
+    }
+
+    #[inline]
+    pub const fn rotate_clockwise_inplace(&mut self) {
+        *self = self.rotate_clockwise();
+    }
+
+    #[inline]
+    pub const fn rotate_counter_clockwise_inplace(&mut self) {
+        *self = self.rotate_counter_clockwise();
+    }
+}
+
+/// A line of text whose length differs from the first line's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RaggedRow {
+    pub y: usize,
+    pub len: usize,
+    pub expected: usize,
+}
+
+impl fmt::Display for RaggedRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {} is {} tiles wide, expected {}", self.y, self.len, self.expected)
+    }
+}
+
+/// Rectangular grid stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    data: Vec<T>,
+    w: usize,
+    h: usize,
+}
+
+impl<T> Grid<T> {
+    #[inline]
+    pub fn new(w: usize, h: usize, data: Vec<T>) -> Self {
+        assert!(w * h == data.len(), "Invalid dimensions");
+        Self { data, w, h }
+    }
+
+    /// Builds every cell from its position, row by row.
+    #[inline]
+    pub fn from_fn<F: FnMut((usize, usize)) -> T>(w: usize, h: usize, f: F) -> Self {
+        Self::new(w, h, (0..h).flat_map(|y| (0..w).map(move |x| (x, y))).map(f).collect())
+    }
+
+    /// Reads one cell per byte of every line, with the whitespace around each line trimmed.
+    #[inline]
+    pub fn parse<E, F>(input: &str, mut cell: F) -> Result<Self, E>
+    where
+        E: From<RaggedRow>,
+        F: FnMut((usize, usize), u8) -> Result<T, E>,
+    {
+        let rows = input.trim_end().lines().map(str::trim).collect::<Vec<_>>();
+        let w = rows.first().map_or(0, |row| row.len());
+        let mut data = Vec::with_capacity(w * rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != w {
+                return Err(RaggedRow {
+                    y,
+                    len: row.len(),
+                    expected: w,
+                }
+                .into());
+            }
+            for (x, byte) in row.bytes().enumerate() {
+                data.push(cell((x, y), byte)?);
+            }
+        }
+        Ok(Self::new(w, rows.len(), data))
+    }
+
+    #[inline]
+    pub const fn width(&self) -> usize {
+        self.w
+    }
+
+    #[inline]
+    pub const fn height(&self) -> usize {
+        self.h
+    }
+
+    /// `None` out of bounds.
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        (x < self.w && y < self.h).then(|| &self.data[y * self.w + x])
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
+        (x < self.w && y < self.h).then(|| &mut self.data[y * self.w + x])
+    }
+
+    /// Like [`Self::get`], with negative coordinates out of bounds too.
+    #[inline]
+    pub fn get_signed(&self, x: isize, y: isize) -> Option<&T> {
+        self.get(usize::try_from(x).ok()?, usize::try_from(y).ok()?)
+    }
+
+    /// Treats the grid as a torus, so every coordinate wraps around to a cell. Panics on an empty grid.
+    #[inline]
+    #[cfg_attr(not(test), expect(dead_code, reason = "no day walks a torus yet, the tests cover it"))]
+    pub fn get_wrapping(&self, x: isize, y: isize) -> &T {
+        assert!(self.w > 0 && self.h > 0, "An empty grid has no cells to wrap around to");
+        let (x, y) = (x.rem_euclid(self.w as isize) as usize, y.rem_euclid(self.h as isize) as usize);
+        &self.data[y * self.w + x]
+    }
+
+    /// # Safety
+    ///
+    /// `x` must be less than the width and `y` less than the height.
+    #[inline]
+    pub unsafe fn get_unchecked(&self, x: usize, y: usize) -> &T {
+        debug_assert!(x < self.w && y < self.h, "Position ({}, {}) out of bounds", x, y);
+        // SAFETY: the caller keeps the position inside the grid, so the index is inside `data`
+        unsafe { self.data.get_unchecked(y * self.w + x) }
+    }
+
+    /// Every cell, row by row.
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.data.iter_mut()
+    }
+
+    #[inline]
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[T]> + ExactSizeIterator {
+        // a grid without columns has no cells to chunk
+        self.data.chunks_exact(self.w.max(1))
+    }
+
+    /// The cells of column `x` from top to bottom.
+    #[inline]
+    pub fn column(&self, x: usize) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+        assert!(x < self.w, "Column out of bounds");
+        // SAFETY: `x` was just checked and `y` stays below the height
+        (0..self.h).map(move |y| unsafe { self.get_unchecked(x, y) })
+    }
+
+    /// The in-bounds neighbours of `pos` in each of `dirs`, e.g. [`Direction::CARDINALS`] or [`Direction::ALL`].
+    #[inline]
+    pub fn neighbors<'d>(&self, (x, y): (usize, usize), dirs: &'d [Direction]) -> impl Iterator<Item = (Direction, (usize, usize))> + 'd {
+        let (w, h) = (self.w, self.h);
+        dirs.iter().filter_map(move |&dir| Some((dir, dir.offset_bounded(x, y, w, h)?)))
+    }
+
+    #[inline]
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid::new(self.w, self.h, self.data.iter().map(f).collect())
+    }
+
+    /// One line per row, with `f` drawing every cell.
+    #[inline]
+    pub fn render<F, R>(&self, f: F) -> String
+    where
+        R: AsRef<str>,
+        F: Fn(&T) -> R,
+    {
+        let mut s = String::with_capacity((self.w + 1) * self.h);
+        for row in self.rows() {
+            for cell in row {
+                s.push_str(f(cell).as_ref());
+            }
+            s.push('\n');
+        }
+        s
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+    #[inline]
+    fn index(&self, (x, y): (usize, usize)) -> &T {
+        self.get(x, y).unwrap_or_else(
+            #[cold]
+            || panic!("Position ({}, {}) out of bounds", x, y),
+        )
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    #[inline]
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut T {
+        self.get_mut(x, y).unwrap_or_else(
+            #[cold]
+            || panic!("Position ({}, {}) out of bounds", x, y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors_agree_inside_and_wrap_outside() {
+        let grid = Grid::from_fn(3, 2, |(x, y)| y * 3 + x);
+        for (x, y) in (0..2).flat_map(|y| (0..3).map(move |x| (x, y))) {
+            let (sx, sy) = (x as isize, y as isize);
+            assert_eq!(grid.get(x, y), Some(&grid[(x, y)]), "get at ({}, {})", x, y);
+            assert_eq!(grid.get_signed(sx, sy), Some(&grid[(x, y)]), "get_signed at ({}, {})", x, y);
+            // SAFETY: both coordinates are inside the grid
+            assert_eq!(unsafe { grid.get_unchecked(x, y) }, &grid[(x, y)], "get_unchecked at ({}, {})", x, y);
+            for (dx, dy) in [(0, 0), (3, 0), (-3, 2), (-6, -4), (30, -20)] {
+                assert_eq!(
+                    grid.get_wrapping(sx + dx, sy + dy),
+                    &grid[(x, y)],
+                    "get_wrapping at ({}, {})",
+                    sx + dx,
+                    sy + dy
+                );
+            }
+        }
+        assert_eq!(grid.get(3, 0), None, "past the last column");
+        assert_eq!(grid.get_signed(-1, 0), None, "before the first column");
+        assert_eq!(grid.get_signed(0, 2), None, "past the last row");
+    }
+
+    #[test]
+    #[should_panic = "An empty grid has no cells to wrap around to"]
+    fn wrapping_on_an_empty_grid_panics() {
+        Grid::<u8>::from_fn(0, 4, |_| 0).get_wrapping(1, 1);
+    }
+}
@@ -1,3 +1,4 @@
+use cli::Options;
 use puzzles::*;
 use solver::SolverSentinel;
 use std::sync::LazyLock;
@@ -16,10 +17,13 @@ lazy_file_include!(INPUT5, "input5.txt");
 lazy_file_include!(INPUT6, "input6.txt");
 lazy_file_include!(INPUT7, "input7.txt");
 
+mod cli;
+mod grid;
 mod puzzles;
 mod solver;
 
 fn main() {
+    let options = Options::from_env();
     let mut sentinel = SolverSentinel::new();
 
     // day 1
@@ -37,18 +41,108 @@ fn main() {
     // day 4
     sentinel.solve(4, 1, &INPUT4, puzzle4::solve_part_1);
     sentinel.solve(4, 2, &INPUT4, puzzle4::solve_part_2);
+    if !options.day4_words.is_empty() {
+        puzzle4::print_words(&INPUT4, &options.day4_words, &sentinel);
+    }
+    if !options.day4_patterns.is_empty() {
+        puzzle4::print_patterns(&INPUT4, &options.day4_patterns, &sentinel);
+    }
 
     // day 5
     sentinel.solve(5, 1, &INPUT5, puzzle5::solve_part_1);
-    // unsolved
-    // sentinel.solve(5, 2, &INPUT5, puzzle5::solve_part_2);
+    sentinel.solve(5, 2, &INPUT5, puzzle5::solve_part_2);
+    if options.day5_explain {
+        puzzle5::print_rejections(&INPUT5, &sentinel);
+    }
+    if options.day5_analyze {
+        puzzle5::analysis::print_analysis(&INPUT5, &sentinel);
+    }
+    if let Some(ref path) = options.day5_dot {
+        if let Err(err) = puzzle5::analysis::write_dot(&INPUT5, path) {
+            eprintln!("Failed to write {}: {}", path.display(), err);
+        }
+    }
+    if options.day5_rank {
+        puzzle5::repair::print_ranking(&INPUT5, &sentinel);
+    }
+    if !options.day5_edits.is_empty() {
+        puzzle5::live::print_edits(&INPUT5, &options.day5_edits, &sentinel);
+    }
 
     // day 6
-    sentinel.solve(6, 1, &INPUT6, puzzle6::solve_part_1);
-    sentinel.solve(6, 2, &INPUT6, puzzle6::solve_part_2);
+    if options.day6_sparse {
+        sentinel.solve(6, 1, &INPUT6, |input, sentinel| {
+            puzzle6::solve_part_1_sparse(input, sentinel, options.day6_turn_policy)
+        });
+    } else {
+        sentinel.solve(6, 1, &INPUT6, |input, sentinel| {
+            puzzle6::solve_part_1(input, sentinel, options.day6_turn_policy)
+        });
+    }
+    if options.day6_path {
+        puzzle6::print_patrol(&INPUT6, options.day6_turn_policy, &sentinel);
+    }
+    if options.day6_render {
+        puzzle6::render::print_render(&INPUT6, options.day6_turn_policy, options.day6_style, &sentinel);
+    }
+    if options.day6_loops {
+        puzzle6::print_looping_obstacles(&INPUT6, options.day6_turn_policy, &sentinel);
+    }
+    if options.day6_loops_render {
+        puzzle6::render::print_looping_obstacles(&INPUT6, options.day6_turn_policy, options.day6_style, &sentinel);
+    }
+    if let Some(pos) = options.day6_obstacle {
+        if let Err(err) = puzzle6::render::print_obstacle(&INPUT6, options.day6_turn_policy, pos, options.day6_style, &sentinel) {
+            eprintln!("{}", err);
+        }
+    }
+    if let Some(runs) = options.day6_bench {
+        puzzle6::bench::print_benchmarks(&INPUT6, options.day6_turn_policy, runs, &sentinel);
+    }
+    if options.day6_heatmap {
+        puzzle6::heatmap::print_heatmaps(&INPUT6, options.day6_turn_policy, &sentinel);
+    }
+    if let Some(ref dir) = options.day6_heatmap_ppm {
+        if let Err(err) = puzzle6::heatmap::write_heatmaps(&INPUT6, options.day6_turn_policy, dir) {
+            eprintln!("Failed to write heatmaps to {}: {}", dir.display(), err);
+        }
+    }
+    if let Some(ref path) = options.day6_guards {
+        if let Err(err) = puzzle6::guards::print_guards(path, options.day6_turn_policy, &sentinel) {
+            eprintln!("{}", err);
+        }
+    }
+    let frame_every = options.day6_frame_every.unwrap_or(1);
+    if let Some(delay) = options.day6_animate {
+        puzzle6::render::animate(&INPUT6, options.day6_turn_policy, delay, frame_every, &sentinel);
+    }
+    if let Some(ref dir) = options.day6_frames {
+        if let Err(err) = puzzle6::render::dump_frames(&INPUT6, options.day6_turn_policy, dir, frame_every) {
+            eprintln!("Failed to write frames to {}: {}", dir.display(), err);
+        }
+    }
+    if options.day6_sparse {
+        sentinel.solve(6, 2, &INPUT6, |input, sentinel| {
+            puzzle6::solve_part_2_sparse(input, sentinel, options.day6_turn_policy, options.day6_threads)
+        });
+    } else if options.day6_sequential {
+        sentinel.solve(6, 2, &INPUT6, |input, sentinel| {
+            puzzle6::solve_part_2(input, sentinel, options.day6_turn_policy)
+        });
+    } else if options.day6_jump {
+        sentinel.solve(6, 2, &INPUT6, |input, sentinel| {
+            puzzle6::solve_part_2_jump(input, sentinel, options.day6_turn_policy, options.day6_threads)
+        });
+    } else {
+        sentinel.solve(6, 2, &INPUT6, |input, sentinel| {
+            puzzle6::solve_part_2_parallel(input, sentinel, options.day6_turn_policy, options.day6_threads)
+        });
+    }
 
     // day 7
-    sentinel.solve(7, 1, &INPUT7, puzzle7::solve_part_1);
+    sentinel.solve(7, 1, &INPUT7, |input, sentinel| {
+        puzzle7::solve_part_1(input, sentinel, options.day7_mode, options.day7_print_solutions)
+    });
     // sentinel.solve(7, 2, &INPUT7, puzzle7::solve_part_2);
 
     sentinel.finalize();
@@ -4,4 +4,6 @@ pub mod puzzle3;
 pub mod puzzle4;
 pub mod puzzle5;
 pub mod puzzle6;
-pub mod puzzle7;
\ No newline at end of file
+pub mod puzzle7;
+#[cfg(test)]
+pub mod test_rng;
@@ -45,11 +45,11 @@ pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> usize {
                                     return false;
                                 }
                             }
-                        };
+                        }
 
                         last_number = Some(num_val);
                     }
-                };
+                }
             }
 
             true
@@ -191,11 +191,11 @@ pub fn check(nums: &[u32]) -> bool {
                             return false;
                         }
                     }
-                };
+                }
 
                 last_number = Some(num);
             }
-        };
+        }
     }
 
     true
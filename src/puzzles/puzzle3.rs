@@ -95,12 +95,12 @@ impl<'a> Scanner<'a> {
     }
 
     #[inline]
-    pub fn scan_tokens(&mut self) -> &[Token] {
+    pub fn scan_tokens(&mut self) -> &[Token<'_>] {
         while !self.is_at_end() {
             self.start = self.index;
             if self.scan_token().is_none() {
                 eprintln!("Error: {:?}", self);
-            };
+            }
         }
 
         self.tokens.push(Token::Eof);
@@ -168,7 +168,7 @@ impl<'a> Scanner<'a> {
                 self.parse_number_literal();
             }
             _ => self.add_basic_token(Token::Invalid),
-        };
+        }
 
         Some(())
     }
@@ -267,37 +267,31 @@ impl<'a> Compiler<'a> {
     pub fn parse_token(&mut self) -> Option<()> {
         let token = self.advance()?;
         match token {
-            Token::Mul => {
-                if self.match1(Token::OpenParen) {
-                    if let Token::Num(lit1) = self.advance()? {
-                        if self.match1(Token::Comma) {
-                            if let Token::Num(lit2) = self.advance()? {
-                                if self.match1(Token::CloseParen) && !self.dont_fired {
-                                    let num1_lit = str::from_utf8(lit1).unwrap();
-                                    let num2_lit = str::from_utf8(lit2).unwrap();
-
-                                    let num1 = num1_lit.parse::<i32>().unwrap();
-                                    let num2 = num2_lit.parse::<i32>().unwrap();
-
-                                    self.add_num(num1 * num2);
-                                }
+            Token::Mul if self.match1(Token::OpenParen) => {
+                if let Token::Num(lit1) = self.advance()? {
+                    if self.match1(Token::Comma) {
+                        if let Token::Num(lit2) = self.advance()? {
+                            if self.match1(Token::CloseParen) && !self.dont_fired {
+                                let num1_lit = str::from_utf8(lit1).unwrap();
+                                let num2_lit = str::from_utf8(lit2).unwrap();
+
+                                let num1 = num1_lit.parse::<i32>().unwrap();
+                                let num2 = num2_lit.parse::<i32>().unwrap();
+
+                                self.add_num(num1 * num2);
                             }
                         }
                     }
-                };
-            }
-            Token::Do => {
-                if self.mode == Part::Two {
-                    self.dont_fired = false;
                 }
             }
-            Token::Dont => {
-                if self.mode == Part::Two {
-                    self.dont_fired = true;
-                }
+            Token::Do if self.mode == Part::Two => {
+                self.dont_fired = false;
+            }
+            Token::Dont if self.mode == Part::Two => {
+                self.dont_fired = true;
             }
             _ => {}
-        };
+        }
 
         Some(())
     }
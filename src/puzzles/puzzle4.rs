@@ -1,195 +1,267 @@
+use crate::grid::{Direction, Grid, RaggedRow};
+use crate::sentinel_println;
 use crate::solver::SolverSentinel;
+use core::fmt;
+use core::iter;
+use dictionary::Dictionary;
+use pattern::{Pattern, Placement};
+
+pub mod dictionary;
+pub mod pattern;
+
+// one direction of every opposite pair, for words that read the same both ways
+const ONE_WAY: [Direction; 4] = [Direction::Right, Direction::DownRight, Direction::Down, Direction::DownLeft];
+
+/// Where a word starts and which way it reads from there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WordMatch {
+    pub start: (usize, usize),
+    pub dir: Direction,
+}
+
+impl fmt::Display for WordMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} reading {:?}", self.start, self.dir)
+    }
+}
 
 #[derive(Clone, PartialEq, Eq, Hash)]
-struct String2D<'a> {
-    lines: Vec<&'a [u8]>,
-    width: usize,
-    height: usize,
+struct String2D {
+    grid: Grid<u8>,
 }
 
-impl<'a> String2D<'a> {
+impl String2D {
     #[inline]
-    pub fn from(s: &'a [u8]) -> Option<Self> {
-        let lines: Vec<_> = s.split(|n| *n == b'\n' || *n == b'\r').collect();
-        let width = lines.first().map(|l| l.len())?;
-        let height = lines.len();
-        Some(Self { lines, width, height })
+    pub fn new(input: &str) -> Result<Self, RaggedRow> {
+        let grid = Grid::parse(input, |_, byte| Ok::<_, RaggedRow>(byte))?;
+        Ok(Self { grid })
     }
 
     #[inline]
     pub fn get_2d(&self, x: usize, y: usize) -> Option<u8> {
-        self.lines.get(y)?.get(x).copied()
+        self.grid.get(x, y).copied()
     }
 
+    /// Every placement of `word` in the grid, reading in any of the eight directions.
+    ///
+    /// A palindrome reads the same from its other end, so it's only reported in one direction of every opposite
+    /// pair, and a single letter is reported once per cell.
     #[inline]
-    pub fn check_xmas_successively_for_one_direction(&self, x: usize, y: usize, direction: Direction) -> bool {
-        let Some((nx, ny)) = direction.offset_times(x, y, 3) else { return false };
-        if self.get_2d(nx, ny) != Some(b'S') {
-            return false;
-        }
-
-        let Some((nx, ny)) = direction.offset_times(x, y, 2) else { return false };
-        if self.get_2d(nx, ny) != Some(b'A') {
-            return false;
-        }
-
-        let Some((nx, ny)) = direction.offset_times(x, y, 1) else { return false };
-        if self.get_2d(nx, ny) != Some(b'M') {
-            return false;
+    pub fn find_word(&self, word: &str) -> Vec<WordMatch> {
+        let word = word.as_bytes();
+        let Some((&first, rest)) = word.split_first() else {
+            return Vec::new();
+        };
+        let starts = self
+            .grid
+            .rows()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().filter(move |&(_, &c)| c == first).map(move |(x, _)| (x, y)));
+        if rest.is_empty() {
+            return starts
+                .map(|start| WordMatch {
+                    start,
+                    dir: Direction::Right,
+                })
+                .collect();
         }
+        let dirs: &[Direction] = if word.iter().eq(word.iter().rev()) { &ONE_WAY } else { &Direction::ALL };
+        starts
+            .flat_map(|start| {
+                self.grid
+                    .neighbors(start, dirs)
+                    .filter(move |&(dir, _)| {
+                        rest.iter()
+                            .zip(1..)
+                            .all(|(&c, i)| dir.offset_times(start.0, start.1, i).and_then(|(x, y)| self.get_2d(x, y)) == Some(c))
+                    })
+                    .map(move |(dir, _)| WordMatch { start, dir })
+            })
+            .collect()
+    }
 
-        true
+    /// Every maximal line of cells running in `dir`, as its first cell and its letters.
+    #[inline]
+    pub fn lines(&self, dir: Direction) -> impl Iterator<Item = ((usize, usize), Vec<u8>)> + '_ {
+        let (w, h) = (self.grid.width(), self.grid.height());
+        let back = dir.reversed();
+        (0..h)
+            .flat_map(move |y| (0..w).map(move |x| (x, y)))
+            .filter(move |&(x, y)| back.offset_bounded(x, y, w, h).is_none())
+            .map(move |start| {
+                let cells = iter::successors(Some(start), |&(x, y)| dir.offset_bounded(x, y, w, h));
+                (start, cells.map(|pos| self.grid[pos]).collect())
+            })
     }
 
+    /// Every placement of every word, found in one pass of a [`Dictionary`] over each line of each of the eight
+    /// directions. The matches of `words[i]` end up at index `i`, counted like [`Self::find_word`] counts them.
     #[inline]
-    pub fn check_xmas_successively_for_all_directions(&self, x: usize, y: usize) -> usize {
-        let mut count = 0;
-        for d in Direction::ALL {
-            if self.check_xmas_successively_for_one_direction(x, y, d) {
-                count += 1;
+    pub fn find_words<S: AsRef<str>>(&self, words: &[S]) -> Vec<Vec<WordMatch>> {
+        let dictionary = Dictionary::new(words);
+        let mut found = vec![Vec::new(); words.len()];
+        for dir in Direction::ALL {
+            for ((x, y), letters) in self.lines(dir) {
+                for (end, index) in dictionary.find(&letters) {
+                    let word = words[index].as_ref().as_bytes();
+                    let counts = match word.len() {
+                        1 => dir == Direction::Right,
+                        _ if word.iter().eq(word.iter().rev()) => ONE_WAY.contains(&dir),
+                        _ => true,
+                    };
+                    if counts {
+                        let start = dir.offset_times(x, y, end + 1 - word.len()).unwrap();
+                        found[index].push(WordMatch { start, dir });
+                    }
+                }
             }
         }
-        count
+        found
     }
 
     #[inline]
-    pub fn check_xmas_all_locations_for_all_directions(&self) -> usize {
-        self.lines.iter().enumerate().fold(0_usize, |count, (y, line)| {
-            count
-                + line
-                    .iter()
-                    .enumerate()
-                    .filter(|&(_, &c)| c == b'X')
-                    .map(|(x, _)| self.check_xmas_successively_for_all_directions(x, y))
-                    .sum::<usize>()
-        })
+    pub fn find_pattern(&self, pattern: &Pattern) -> Vec<Placement> {
+        pattern.placements(&self.grid)
     }
+}
 
-    #[inline]
-    pub fn check_mas(&self, ax: usize, ay: usize) -> bool {
-        let Some((x, y)) = Direction::TopRight.offset(ax, ay) else {
-            return false;
-        };
-
-        match self.get_2d(x, y) {
-            Some(b'S') => {
-                let Some((x, y)) = Direction::BottomLeft.offset(ax, ay) else {
-                    return false;
-                };
-                if self.get_2d(x, y) != Some(b'M') {
-                    return false;
-                }
-            }
-            Some(b'M') => {
-                let Some((x, y)) = Direction::BottomLeft.offset(ax, ay) else {
-                    return false;
-                };
-                if self.get_2d(x, y) != Some(b'S') {
-                    return false;
-                }
-            }
-            _ => return false,
-        };
+#[inline]
+pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> usize {
+    let input = String2D::new(input).unwrap();
+    input.find_word("XMAS").len()
+}
 
-        let Some((x, y)) = Direction::TopLeft.offset(ax, ay) else { return false };
+#[inline]
+pub fn solve_part_2(input: &str, _sentinel: &mut SolverSentinel) -> usize {
+    let input = String2D::new(input).unwrap();
+    let x_mas = "M.S\n.A.\nM.S".parse::<Pattern>().unwrap();
+    input.find_pattern(&x_mas).len()
+}
 
-        match self.get_2d(x, y) {
-            Some(b'S') => {
-                let Some((x, y)) = Direction::BottomRight.offset(ax, ay) else {
-                    return false;
-                };
-                if self.get_2d(x, y) != Some(b'M') {
-                    return false;
-                }
-            }
-            Some(b'M') => {
-                let Some((x, y)) = Direction::BottomRight.offset(ax, ay) else {
-                    return false;
-                };
-                if self.get_2d(x, y) != Some(b'S') {
-                    return false;
-                }
-            }
-            _ => return false,
+#[inline]
+pub fn print_words(input: &str, words: &[String], sentinel: &SolverSentinel) {
+    let grid = String2D::new(input).unwrap();
+    for (word, matches) in words.iter().zip(grid.find_words(words)) {
+        sentinel_println!(Sentinel: sentinel, println!("day 4: {} occurrences of {}", matches.len(), word));
+        for word_match in &matches {
+            sentinel_println!(Sentinel: sentinel, println!("\t{}", word_match));
         }
-
-        true
-    }
-
-    #[inline]
-    pub fn check_mas_all_locations(&self) -> usize {
-        self.lines.iter().enumerate().fold(0_usize, |count, (y, line)| {
-            count
-                + line
-                    .iter()
-                    .enumerate()
-                    .filter(|&(x, &c)| if c == b'A' { self.check_mas(x, y) } else { false })
-                    .count()
-        })
     }
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-enum Direction {
-    TopLeft,
-    Top,
-    TopRight,
-    BottomLeft,
-    Bottom,
-    BottomRight,
-    Left,
-    Right,
+#[inline]
+pub fn print_patterns(input: &str, patterns: &[Pattern], sentinel: &SolverSentinel) {
+    let grid = String2D::new(input).unwrap();
+    for pattern in patterns {
+        let placements = grid.find_pattern(pattern);
+        sentinel_println!(
+            Sentinel: sentinel,
+            println!(
+                "day 4: {} placements of {} in {} orientations",
+                placements.len(),
+                pattern,
+                pattern.orientations().len()
+            )
+        );
+        for placement in &placements {
+            sentinel_println!(Sentinel: sentinel, println!("\t{}", placement));
+        }
+    }
 }
 
-impl Direction {
-    pub const ALL: [Self; 8] = [
-        Self::TopLeft,
-        Self::Top,
-        Self::TopRight,
-        Self::Left,
-        Self::Right,
-        Self::BottomLeft,
-        Self::Bottom,
-        Self::BottomRight,
-    ];
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+    use alloc::collections::BTreeSet;
 
-    #[inline]
-    pub fn offset(self, x: usize, y: usize) -> Option<(usize, usize)> {
-        Some(match self {
-            Self::TopLeft => (x.checked_sub(1)?, y.checked_sub(1)?),
-            Self::Top => (x, y.checked_sub(1)?),
-            Self::TopRight => (x.checked_add(1)?, y.checked_sub(1)?),
-            Self::Left => (x.checked_sub(1)?, y),
-            Self::Right => (x.checked_add(1)?, y),
-            Self::BottomLeft => (x.checked_sub(1)?, y.checked_add(1)?),
-            Self::Bottom => (x, y.checked_add(1)?),
-            Self::BottomRight => (x.checked_add(1)?, y.checked_add(1)?),
+    // random grids over a few letters, so words show up often
+    fn random_grids() -> impl Iterator<Item = String2D> {
+        let mut rng = Xorshift::new();
+        (0..200).map(move |_| {
+            let (w, h) = (rng.below(8) + 1, rng.below(8) + 1);
+            let grid = Grid::from_fn(w, h, |_| b"XMAS"[rng.below(4)]);
+            String2D { grid }
         })
     }
 
-    #[inline]
-    pub fn offset_times(self, x: usize, y: usize, factor: usize) -> Option<(usize, usize)> {
-        Some(match self {
-            Self::TopLeft => (x.checked_sub(factor)?, y.checked_sub(factor)?),
-            Self::Top => (x, y.checked_sub(factor)?),
-            Self::TopRight => (x.checked_add(factor)?, y.checked_sub(factor)?),
-            Self::Left => (x.checked_sub(factor)?, y),
-            Self::Right => (x.checked_add(factor)?, y),
-            Self::BottomLeft => (x.checked_sub(factor)?, y.checked_add(factor)?),
-            Self::Bottom => (x, y.checked_add(factor)?),
-            Self::BottomRight => (x.checked_add(factor)?, y.checked_add(factor)?),
-        })
+    // distinct sets of cells one of `shapes` covers letter for letter, wherever on the grid it's placed
+    fn distinct_cell_sets(grid: &String2D, shapes: &[Vec<((isize, isize), u8)>]) -> usize {
+        let (w, h) = (grid.grid.width() as isize, grid.grid.height() as isize);
+        let mut found = BTreeSet::new();
+        for shape in shapes {
+            for (ox, oy) in (-8..h + 8).flat_map(|y| (-8..w + 8).map(move |x| (x, y))) {
+                let cells = shape.iter().map(|&((x, y), letter)| ((ox + x, oy + y), letter)).collect::<Vec<_>>();
+                if cells.iter().all(|&((x, y), letter)| grid.grid.get_signed(x, y) == Some(&letter)) {
+                    found.insert(cells.into_iter().map(|cell| cell.0).collect::<BTreeSet<_>>());
+                }
+            }
+        }
+        found.len()
     }
-}
 
-#[inline]
-pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> usize {
-    let input = String2D::from(input.as_bytes()).unwrap();
-    input.check_xmas_all_locations_for_all_directions()
-}
+    #[test]
+    fn words_match_brute_force() {
+        let words = ["XMAS", "X", "MAM", "SAS", "XMX", "AA", "SS", "MAS", "XMAS", "SAMX", "Q", ""];
+        for grid in random_grids() {
+            let all = grid.find_words(&words);
+            for (word, one_pass) in words.iter().zip(all) {
+                let shapes = Direction::ALL
+                    .map(|dir| {
+                        let (dx, dy) = dir.delta();
+                        word.bytes().zip(0..).map(|(letter, i)| ((dx * i, dy * i), letter)).collect()
+                    })
+                    .to_vec();
+                let matches = grid.find_word(word);
+                let expected = if word.is_empty() { 0 } else { distinct_cell_sets(&grid, &shapes) };
+                assert_eq!(
+                    matches.len(),
+                    expected,
+                    "{} in\n{}",
+                    word,
+                    grid.grid.render(|&c| char::from(c).to_string())
+                );
+                let sorted = |found: &[WordMatch]| found.iter().map(|m| (m.start, m.dir as usize)).collect::<BTreeSet<_>>();
+                assert_eq!(sorted(&one_pass), sorted(&matches), "one pass for {}", word);
+                assert_eq!(one_pass.len(), matches.len(), "no duplicates for {}", word);
+            }
+        }
+    }
 
-#[inline]
-pub fn solve_part_2(input: &str, _sentinel: &mut SolverSentinel) -> usize {
-    let input = String2D::from(input.as_bytes()).unwrap();
-    input.check_mas_all_locations()
+    #[test]
+    fn patterns_match_brute_force() {
+        for pattern in ["M.S/.A./M.S", ".M./MAS/.S.", "AA/AA", "XM/A.", "X", "S..X", "MA/SX/AM"] {
+            let cells = pattern.split('/').zip(0..).flat_map(|(row, y)| {
+                row.bytes()
+                    .zip(0..)
+                    .filter(|cell| cell.0 != b'.')
+                    .map(move |(letter, x)| ((x, y), letter))
+            });
+            let cells = cells.collect::<Vec<_>>();
+            // all four quarter turns of the template and of its mirror image
+            let shapes = [1, -1]
+                .iter()
+                .flat_map(|&mirror| {
+                    let cells = &cells;
+                    (0..4).map(move |turns| {
+                        cells
+                            .iter()
+                            .map(|&((x, y), letter)| ((0..turns).fold((x * mirror, y), |(x, y), _| (-y, x)), letter))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            let parsed = pattern.replace('/', "\n").parse::<Pattern>().unwrap();
+            for grid in random_grids() {
+                assert_eq!(
+                    grid.find_pattern(&parsed).len(),
+                    distinct_cell_sets(&grid, &shapes),
+                    "{} in\n{}",
+                    pattern,
+                    grid.grid.render(|&c| char::from(c).to_string())
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,88 @@
+extern crate alloc;
+use ahash::AHashMap;
+use alloc::collections::VecDeque;
+
+/// Aho-Corasick automaton: a trie of the words with failure links, so a single pass over a text finds every
+/// occurrence of every word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dictionary {
+    // state 0 is the root, the empty prefix
+    children: Vec<AHashMap<u8, usize>>,
+    // longest proper suffix of the state's prefix that is a state too
+    fail: Vec<usize>,
+    // every word ending at the state, including the ones reached through `fail`
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Dictionary {
+    /// Words are told apart by their index into `words`. Empty words never match.
+    #[inline]
+    pub fn new<S: AsRef<str>>(words: &[S]) -> Self {
+        let mut dictionary = Self {
+            children: vec![AHashMap::new()],
+            fail: vec![0],
+            outputs: vec![Vec::new()],
+        };
+        for (index, word) in words.iter().enumerate() {
+            let word = word.as_ref().as_bytes();
+            if word.is_empty() {
+                continue;
+            }
+            let mut state = 0;
+            for &letter in word {
+                if let Some(&next) = dictionary.children[state].get(&letter) {
+                    state = next;
+                    continue;
+                }
+                dictionary.children.push(AHashMap::new());
+                dictionary.fail.push(0);
+                dictionary.outputs.push(Vec::new());
+                let next = dictionary.children.len() - 1;
+                dictionary.children[state].insert(letter, next);
+                state = next;
+            }
+            dictionary.outputs[state].push(index);
+        }
+
+        // breadth first, so the failure link of every state is done before its children need it
+        let mut queue = dictionary.children[0].values().copied().collect::<VecDeque<_>>();
+        while let Some(state) = queue.pop_front() {
+            let children = dictionary.children[state]
+                .iter()
+                .map(|(&letter, &child)| (letter, child))
+                .collect::<Vec<_>>();
+            for (letter, child) in children {
+                let fail = dictionary.next(dictionary.fail[state], letter);
+                dictionary.fail[child] = fail;
+                let inherited = dictionary.outputs[fail].clone();
+                dictionary.outputs[child].extend(inherited);
+                queue.push_back(child);
+            }
+        }
+        dictionary
+    }
+
+    fn next(&self, mut state: usize, letter: u8) -> usize {
+        loop {
+            if let Some(&next) = self.children[state].get(&letter) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.fail[state];
+        }
+    }
+
+    /// Every occurrence in `text` as the index of its last letter and the index of the word.
+    #[inline]
+    pub fn find(&self, text: &[u8]) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut state = 0;
+        for (end, &letter) in text.iter().enumerate() {
+            state = self.next(state, letter);
+            found.extend(self.outputs[state].iter().map(|&word| (end, word)));
+        }
+        found
+    }
+}
@@ -0,0 +1,136 @@
+use crate::grid::{Grid, RaggedRow};
+use core::fmt;
+use core::str::FromStr;
+use itertools::Itertools;
+
+/// A rectangular 2D template of letters, one line per row, where `.` matches any letter.
+///
+/// Rows and columns that are wildcards only are trimmed off, so two placements never differ by a wildcard margin.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    cells: Grid<Option<u8>>,
+}
+
+/// How a pattern was turned before it was laid onto the grid: mirrored left to right first, then rotated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Orientation {
+    pub mirrored: bool,
+    pub quarter_turns: u8,
+}
+
+/// A pattern found with its top-left corner at `corner`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub corner: (usize, usize),
+    pub orientation: Orientation,
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cells =
+            Grid::parse(s, |_, byte| Ok::<_, RaggedRow>((byte != b'.').then_some(byte))).map_err(|err| format!("Invalid pattern, {}", err))?;
+        let letters = cells
+            .rows()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().positions(Option::is_some).map(move |x| (x, y)))
+            .collect::<Vec<_>>();
+        let (Some(min_x), Some(max_x)) = (letters.iter().map(|pos| pos.0).min(), letters.iter().map(|pos| pos.0).max()) else {
+            return Err(format!("Invalid pattern, it has no letters: {}", s));
+        };
+        let min_y = letters[0].1;
+        let max_y = letters[letters.len() - 1].1;
+        Ok(Self {
+            cells: Grid::from_fn(max_x - min_x + 1, max_y - min_y + 1, |(x, y)| cells[(min_x + x, min_y + y)]),
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row = |row: &[Option<u8>]| row.iter().map(|cell| cell.map_or('.', char::from)).collect::<String>();
+        write!(f, "{}", self.cells.rows().map(row).join("/"))
+    }
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.corner)?;
+        if self.orientation.mirrored {
+            write!(f, " mirrored")?;
+        }
+        match self.orientation.quarter_turns {
+            0 => Ok(()),
+            turns => write!(f, " turned {} degrees clockwise", u32::from(turns) * 90),
+        }
+    }
+}
+
+impl Pattern {
+    /// Quarter turn clockwise.
+    #[inline]
+    #[must_use]
+    pub fn rotated(&self) -> Self {
+        let (w, h) = (self.cells.width(), self.cells.height());
+        Self {
+            cells: Grid::from_fn(h, w, |(x, y)| self.cells[(y, h - 1 - x)]),
+        }
+    }
+
+    /// Mirrored left to right.
+    #[inline]
+    #[must_use]
+    pub fn reflected(&self) -> Self {
+        let w = self.cells.width();
+        Self {
+            cells: Grid::from_fn(w, self.cells.height(), |(x, y)| self.cells[(w - 1 - x, y)]),
+        }
+    }
+
+    /// Every distinct way the pattern can be turned, the unturned pattern first. Symmetric patterns have fewer
+    /// than eight, so no placement is found twice.
+    #[inline]
+    pub fn orientations(&self) -> Vec<(Orientation, Self)> {
+        let mut orientations: Vec<(Orientation, Self)> = Vec::with_capacity(8);
+        for mirrored in [false, true] {
+            let mut turned = if mirrored { self.reflected() } else { self.clone() };
+            for quarter_turns in 0..4 {
+                if orientations.iter().all(|seen| seen.1 != turned) {
+                    orientations.push((Orientation { mirrored, quarter_turns }, turned.clone()));
+                }
+                turned = turned.rotated();
+            }
+        }
+        orientations
+    }
+
+    /// Whether the pattern as it is matches with its top-left corner at `(cx, cy)`.
+    #[inline]
+    pub fn matches_at(&self, grid: &Grid<u8>, (cx, cy): (usize, usize)) -> bool {
+        self.cells.rows().enumerate().all(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .all(|(x, &cell)| cell.is_none_or(|letter| grid.get(cx + x, cy + y) == Some(&letter)))
+        })
+    }
+
+    /// Every placement of the pattern in any of its orientations.
+    #[inline]
+    pub fn placements(&self, grid: &Grid<u8>) -> Vec<Placement> {
+        let mut placements = Vec::new();
+        for (orientation, pattern) in self.orientations() {
+            let (Some(max_x), Some(max_y)) = (
+                grid.width().checked_sub(pattern.cells.width()),
+                grid.height().checked_sub(pattern.cells.height()),
+            ) else {
+                continue;
+            };
+            for corner in (0..=max_y).flat_map(|y| (0..=max_x).map(move |x| (x, y))) {
+                if pattern.matches_at(grid, corner) {
+                    placements.push(Placement { corner, orientation });
+                }
+            }
+        }
+        placements
+    }
+}
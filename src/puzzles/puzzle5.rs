@@ -1,44 +1,454 @@
+use crate::sentinel_println;
 use crate::solver::SolverSentinel;
+use ahash::AHashMap;
+use core::fmt;
+use core::iter;
 use itertools::Itertools;
 
+pub mod analysis;
+pub mod live;
+pub mod repair;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    update: Vec<Page>,
+    cycle: Vec<Page>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "update {} has no valid ordering, its rules contain the cycle {} -> {}",
+            self.update.iter().join(","),
+            self.cycle.iter().join(" -> "),
+            self.cycle[0]
+        )
+    }
+}
+
+/// A rule `before|after` broken by an update that puts `after` first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Violation {
+    pub before: Page,
+    pub after: Page,
+    pub before_pos: usize,
+    pub after_pos: usize,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}|{} is broken, {} is at position {} but {} is already at position {}",
+            self.before, self.after, self.before, self.before_pos, self.after, self.after_pos
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    /// 1-based line number of the update in the input.
+    pub line: usize,
+    pub update: Vec<Page>,
+    pub violations: Vec<Violation>,
+}
+
 #[inline]
-pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> i32 {
+pub fn parse(input: &str) -> (Vec<(Page, Page)>, Vec<Vec<Page>>) {
     let (page_orderings, updates) = input.split("\n\n").collect_tuple::<(&str, &str)>().unwrap();
 
     let orderings = page_orderings
         .lines()
         .map(|line| {
             let (left, right) = line.split('|').collect_tuple::<(&str, &str)>().unwrap();
-            let (left, right) = (left.parse::<u8>().unwrap(), right.parse::<u8>().unwrap());
+            let (left, right) = (left.parse::<Page>().unwrap(), right.parse::<Page>().unwrap());
             (left, right)
         })
         .collect::<Vec<_>>();
 
-    updates
+    let updates = updates
         .lines()
         .map(|update| {
-            let update_nums: Vec<_> = update.split(',').map(|a| a.parse::<u8>().unwrap()).collect();
+            let update_nums: Vec<_> = update.split(',').map(|a| a.parse::<Page>().unwrap()).collect();
             update_nums
         })
-        .filter(|update_nums| {
-            let valid_orderings = orderings
-                .iter()
-                .filter(|&&(l, r)| update_nums.contains(&l) && update_nums.contains(&r))
-                .collect::<Vec<_>>();
+        .collect::<Vec<_>>();
+
+    (orderings, updates)
+}
+
+/// Page identifier as written in the input.
+pub type Page = u32;
+
+/// Maps the pages mentioned by the rules onto dense indices `0..len()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageInterner {
+    pages: Vec<Page>,
+    indices: AHashMap<Page, usize>,
+}
+
+impl PageInterner {
+    #[inline]
+    pub fn intern(&mut self, page: Page) -> usize {
+        *self.indices.entry(page).or_insert_with(|| {
+            self.pages.push(page);
+            self.pages.len() - 1
+        })
+    }
+
+    #[inline]
+    pub fn index_of(&self, page: Page) -> Option<usize> {
+        self.indices.get(&page).copied()
+    }
 
-            for &(l, r) in valid_orderings {
-                if update_nums.iter().position(|&x| x == l).unwrap() > update_nums.iter().position(|&x| x == r).unwrap() {
-                    return false;
+    #[inline]
+    pub fn page(&self, index: usize) -> Page {
+        self.pages[index]
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// Fixed-width bitset over interned page indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PageSet {
+    words: Vec<u64>,
+}
+
+impl PageSet {
+    #[inline]
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    #[inline]
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    #[inline]
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    #[inline]
+    pub fn union_with(&mut self, other: &Self) {
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            *word |= other;
+        }
+    }
+
+    #[inline]
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.words.iter().zip(&other.words).any(|(word, other)| word & other != 0)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_index, &word)| {
+            let mut word = word;
+            iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    word_index * 64 + bit
+                })
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Relation {
+    // rows[a] holds every b with a rule `a|b`
+    Dense(Vec<PageSet>),
+    // sorted followers per page, for rule sets too large for a square bit matrix
+    Sparse(Vec<Vec<usize>>),
+}
+
+/// Before/after relation of the page ordering rules, built once and shared by every update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderingRules {
+    interner: PageInterner,
+    relation: Relation,
+}
+
+impl OrderingRules {
+    /// Largest number of distinct pages kept as a square bit matrix (2 MiB at the limit).
+    pub const DENSE_LIMIT: usize = 4096;
+
+    #[inline]
+    pub fn new(orderings: &[(Page, Page)]) -> Self {
+        let mut interner = PageInterner::default();
+        let edges = orderings
+            .iter()
+            .map(|&(l, r)| (interner.intern(l), interner.intern(r)))
+            .collect::<Vec<_>>();
+        let n = interner.len();
+
+        let relation = if n <= Self::DENSE_LIMIT {
+            let mut rows = vec![PageSet::new(n); n];
+            for (l, r) in edges {
+                rows[l].insert(r);
+            }
+            Relation::Dense(rows)
+        } else {
+            let mut followers = vec![Vec::new(); n];
+            for (l, r) in edges {
+                followers[l].push(r);
+            }
+            for row in &mut followers {
+                row.sort_unstable();
+                row.dedup();
+            }
+            Relation::Sparse(followers)
+        };
+
+        Self { interner, relation }
+    }
+
+    #[inline]
+    pub const fn interner(&self) -> &PageInterner {
+        &self.interner
+    }
+
+    #[inline]
+    pub fn must_precede(&self, a: Page, b: Page) -> bool {
+        match (self.interner.index_of(a), self.interner.index_of(b)) {
+            (Some(a), Some(b)) => self.must_precede_index(a, b),
+            _ => false,
+        }
+    }
+
+    #[inline]
+    pub fn must_precede_index(&self, a: usize, b: usize) -> bool {
+        match self.relation {
+            Relation::Dense(ref rows) => rows[a].contains(b),
+            Relation::Sparse(ref followers) => followers[a].binary_search(&b).is_ok(),
+        }
+    }
+
+    /// Every page index that a rule puts after `index`.
+    #[inline]
+    pub fn followers(&self, index: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self.relation {
+            Relation::Dense(ref rows) => Box::new(rows[index].iter()),
+            Relation::Sparse(ref followers) => Box::new(followers[index].iter().copied()),
+        }
+    }
+
+    /// Single pass over the update: a page is out of place as soon as it must precede a page already seen.
+    #[inline]
+    pub fn is_valid(&self, update: &[Page]) -> bool {
+        // pages no rule mentions can go anywhere
+        let indices = update.iter().filter_map(|&page| self.interner.index_of(page));
+        match self.relation {
+            Relation::Dense(ref rows) => {
+                let mut seen = PageSet::new(self.interner.len());
+                for index in indices {
+                    if rows[index].intersects(&seen) {
+                        return false;
+                    }
+                    seen.insert(index);
                 }
+                true
             }
+            Relation::Sparse(_) => {
+                let mut seen = Vec::with_capacity(update.len());
+                for index in indices {
+                    if seen.iter().any(|&earlier| self.must_precede_index(index, earlier)) {
+                        return false;
+                    }
+                    seen.push(index);
+                }
+                true
+            }
+        }
+    }
+
+    /// Every rule the update breaks, ordered by the position of the page that should have come first.
+    #[inline]
+    pub fn violations(&self, update: &[Page]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (before_pos, &before) in update.iter().enumerate() {
+            for (after_pos, &after) in update[..before_pos].iter().enumerate() {
+                if self.must_precede(before, after) {
+                    violations.push(Violation {
+                        before,
+                        after,
+                        before_pos,
+                        after_pos,
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// Orders the pages of `update` by the rules that mention two of its pages (Kahn's algorithm).
+///
+/// When several pages are free to go next, the one that came first in `update` wins, so pages the rules
+/// don't constrain keep their relative order.
+#[inline]
+pub fn reorder(update: &[Page], rules: &OrderingRules) -> Result<Vec<Page>, CycleError> {
+    let n = update.len();
+    let mut successors = vec![Vec::new(); n];
+    let mut in_degree = vec![0_usize; n];
+    for (l, &left) in update.iter().enumerate() {
+        for (r, &right) in update.iter().enumerate() {
+            if rules.must_precede(left, right) {
+                successors[l].push(r);
+                in_degree[r] += 1;
+            }
+        }
+    }
+
+    let mut placed = vec![false; n];
+    let mut ordered = Vec::with_capacity(n);
+    while let Some(next) = (0..n).find(|&i| !placed[i] && in_degree[i] == 0) {
+        placed[next] = true;
+        ordered.push(update[next]);
+        for &succ in &successors[next] {
+            in_degree[succ] -= 1;
+        }
+    }
+
+    if ordered.len() == n {
+        return Ok(ordered);
+    }
+
+    // every page left over still has an unplaced predecessor, so walking predecessors must run into a cycle
+    let predecessor_of = |page: usize| (0..n).find(|&p| !placed[p] && successors[p].contains(&page)).unwrap();
+    let mut walk = vec![(0..n).find(|&i| !placed[i]).unwrap()];
+    loop {
+        let pred = predecessor_of(*walk.last().unwrap());
+        if let Some(start) = walk.iter().position(|&p| p == pred) {
+            let mut cycle = walk.split_off(start);
+            cycle.reverse();
+            return Err(CycleError {
+                update: update.to_vec(),
+                cycle: cycle.into_iter().map(|i| update[i]).collect(),
+            });
+        }
+        walk.push(pred);
+    }
+}
 
-            true
+/// Explains why every invalid update in `input` was rejected.
+#[inline]
+pub fn rejections(input: &str) -> Vec<Rejection> {
+    let (orderings, updates) = parse(input);
+    let rules = OrderingRules::new(&orderings);
+    // rule lines, then the blank separator line
+    let first_update_line = orderings.len() + 2;
+
+    updates
+        .into_iter()
+        .enumerate()
+        .filter(|indexed| !rules.is_valid(&indexed.1))
+        .map(|(index, update)| Rejection {
+            line: first_update_line + index,
+            violations: rules.violations(&update),
+            update,
         })
-        .map(|upd| upd[upd.len() / 2] as i32)
-        .sum::<i32>()
+        .collect()
+}
+
+#[inline]
+pub fn print_rejections(input: &str, sentinel: &SolverSentinel) {
+    for rejection in rejections(input) {
+        sentinel_println!(
+            Sentinel: sentinel,
+            println!("day 5: line {} ({}) rejected:", rejection.line, rejection.update.iter().join(","))
+        );
+        for violation in &rejection.violations {
+            sentinel_println!(Sentinel: sentinel, println!("\t{}", violation));
+        }
+    }
+}
+
+#[inline]
+pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> u64 {
+    let (orderings, updates) = parse(input);
+    let rules = OrderingRules::new(&orderings);
+
+    updates
+        .iter()
+        .filter(|update_nums| rules.is_valid(update_nums))
+        .map(|upd| upd[upd.len() / 2] as u64)
+        .sum::<u64>()
 }
 
 #[inline]
-pub fn solve_part_2(_input: &str, _sentinel: &mut SolverSentinel) -> i32 {
-    todo!("i give up")
+pub fn solve_part_2(input: &str, sentinel: &mut SolverSentinel) -> u64 {
+    let (orderings, updates) = parse(input);
+    let rules = OrderingRules::new(&orderings);
+
+    updates
+        .iter()
+        .filter(|update_nums| !rules.is_valid(update_nums))
+        .filter_map(|upd| match reorder(upd, &rules) {
+            Ok(ordered) => Some(ordered[ordered.len() / 2] as u64),
+            Err(err) => {
+                sentinel_println!(Sentinel: sentinel, println!("day 5: skipping {}", err));
+                None
+            }
+        })
+        .sum::<u64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+
+    #[test]
+    fn sparse_rules_match_dense_rules() {
+        let mut rng = Xorshift::new();
+        // disjoint pairs of pages far from the tested ones push the page count past the dense limit
+        let filler = (0..OrderingRules::DENSE_LIMIT as Page / 2 + 8)
+            .map(|i| (10_000 + 2 * i, 10_001 + 2 * i))
+            .collect::<Vec<_>>();
+        for _ in 0..50 {
+            // pages on both sides of 255, so none of them fit a byte
+            let rules = (0..rng.below(80))
+                .map(|_| (240 + rng.below(32) as Page, 240 + rng.below(32) as Page))
+                .collect::<Vec<_>>();
+            let dense = OrderingRules::new(&rules);
+            let sparse = OrderingRules::new(&[rules.as_slice(), &filler].concat());
+            assert!(
+                matches!(dense.relation, Relation::Dense(_)),
+                "{} pages fit the bit matrix",
+                dense.interner.len()
+            );
+            assert!(
+                matches!(sparse.relation, Relation::Sparse(_)),
+                "{} pages are past the limit",
+                sparse.interner.len()
+            );
+            for _ in 0..20 {
+                // a page no rule mentions can turn up too
+                let mut update = (240..272).chain([99_999]).collect::<Vec<Page>>();
+                for i in (1..update.len()).rev() {
+                    update.swap(i, rng.below(i + 1));
+                }
+                update.truncate(rng.below(12) + 1);
+                assert_eq!(sparse.is_valid(&update), dense.is_valid(&update), "update {:?}", update);
+                assert_eq!(sparse.violations(&update), dense.violations(&update), "update {:?}", update);
+                assert_eq!(reorder(&update, &sparse), reorder(&update, &dense), "update {:?}", update);
+            }
+        }
+    }
 }
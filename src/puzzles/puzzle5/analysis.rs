@@ -0,0 +1,332 @@
+use super::{parse, OrderingRules, Page, PageSet};
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use core::fmt;
+use core::fmt::Write;
+use itertools::Itertools;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shape {
+    /// Acyclic, and every pair of pages is ordered by some chain of rules.
+    TotalOrder,
+    /// Acyclic, but some pages are never ordered relative to each other.
+    PartialOrder,
+    Cyclic,
+}
+
+/// Whole-input view of the ordering rules, independent of any update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleAnalysis {
+    pub pages: Vec<Page>,
+    pub rules: Vec<(Page, Page)>,
+    /// Strongly connected components with more than one page, or a page with a rule on itself.
+    pub cycles: Vec<Vec<Page>>,
+    pub shape: Shape,
+    /// Pairs `(a, b)`, `a < b`, where the transitive closure demands both `a` before `b` and `b` before `a`.
+    pub contradictions: Vec<(Page, Page)>,
+    /// Rules between two pages of the same cycle, which the cycle implies whichever of them is left out.
+    pub cyclic: Vec<(Page, Page)>,
+    /// Rules between different cycles or pages that a chain of other such rules already implies.
+    pub redundant: Vec<(Page, Page)>,
+}
+
+impl RuleAnalysis {
+    /// Builds the full transitive closure, so this is quadratic in the number of distinct pages.
+    #[inline]
+    pub fn new(orderings: &[(Page, Page)]) -> Self {
+        let rules = orderings.iter().copied().sorted_unstable().dedup().collect::<Vec<_>>();
+        let direct = OrderingRules::new(&rules);
+        let interner = direct.interner();
+        let n = interner.len();
+        let pages = (0..n).map(|index| interner.page(index)).sorted_unstable().collect::<Vec<_>>();
+
+        // warshall over bitset rows: reach[a] ends up holding every page that must come after a
+        let mut reach = (0..n)
+            .map(|a| {
+                let mut row = PageSet::new(n);
+                direct.followers(a).for_each(|b| row.insert(b));
+                row
+            })
+            .collect::<Vec<_>>();
+        for k in 0..n {
+            let through = reach[k].clone();
+            for row in &mut reach {
+                if row.contains(k) {
+                    row.union_with(&through);
+                }
+            }
+        }
+
+        let components = strongly_connected_components(&direct);
+        let component_count = components.len();
+        let mut component_of = vec![0; n];
+        for (index, component) in components.iter().enumerate() {
+            for &page in component {
+                component_of[page] = index;
+            }
+        }
+        let cycles = components
+            .into_iter()
+            .filter(|component| component.len() > 1 || direct.must_precede_index(component[0], component[0]))
+            .map(|component| component.into_iter().map(|index| interner.page(index)).sorted_unstable().collect())
+            .collect::<Vec<_>>();
+
+        let contradictions = (0..n)
+            .tuple_combinations()
+            .filter(|&(a, b)| reach[a].contains(b) && reach[b].contains(a))
+            .map(|(a, b)| (interner.page(a), interner.page(b)))
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .sorted_unstable()
+            .collect::<Vec<_>>();
+
+        let shape = if !cycles.is_empty() {
+            Shape::Cyclic
+        } else if (0..n).tuple_combinations().all(|(a, b)| reach[a].contains(b) || reach[b].contains(a)) {
+            Shape::TotalOrder
+        } else {
+            Shape::PartialOrder
+        };
+
+        // inside a cycle every rule is implied by going around the others, so redundancy is only asked of rules
+        // between components, on the condensation where each cycle is a single node
+        let component_rule = |a: Page, b: Page| (component_of[interner.index_of(a).unwrap()], component_of[interner.index_of(b).unwrap()]);
+        let (cyclic, between): (Vec<_>, Vec<_>) = rules.iter().copied().partition(|&(a, b)| {
+            let (from, to) = component_rule(a, b);
+            from == to
+        });
+        let mut condensed = vec![Vec::new(); component_count];
+        for &(a, b) in &between {
+            let (from, to) = component_rule(a, b);
+            condensed[from].push(to);
+        }
+        // the closure can't tell whether a path runs through the rule itself, so every edge of the condensation gets a
+        // search of its own with just that edge left out
+        let implied_by_others = |from: usize, to: usize| {
+            let mut seen = vec![false; condensed.len()];
+            seen[from] = true;
+            let mut stack = vec![from];
+            while let Some(component) = stack.pop() {
+                for &next in &condensed[component] {
+                    if (component, next) == (from, to) || seen[next] {
+                        continue;
+                    }
+                    if next == to {
+                        return true;
+                    }
+                    seen[next] = true;
+                    stack.push(next);
+                }
+            }
+            false
+        };
+        let redundant = between
+            .into_iter()
+            .filter(|&(a, b)| {
+                let (from, to) = component_rule(a, b);
+                implied_by_others(from, to)
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            pages,
+            rules,
+            cycles,
+            shape,
+            contradictions,
+            cyclic,
+            redundant,
+        }
+    }
+
+    /// Whether some ordering of all pages satisfies every rule; a rule `a|a` is inconsistent on its own.
+    #[inline]
+    pub const fn is_consistent(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    /// Graphviz rendering of the rules: cycles are boxed into clusters and redundant rules are dashed.
+    #[inline]
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph rules {\n");
+        for (index, component) in self.cycles.iter().enumerate() {
+            writeln!(dot, "    subgraph cluster_{} {{", index).unwrap();
+            writeln!(dot, "        label=\"cycle {}\";", index).unwrap();
+            writeln!(dot, "        {};", component.iter().join("; ")).unwrap();
+            writeln!(dot, "    }}").unwrap();
+        }
+        for &(a, b) in &self.rules {
+            if self.redundant.binary_search(&(a, b)).is_ok() {
+                writeln!(dot, "    {} -> {} [style=dashed];", a, b).unwrap();
+            } else {
+                writeln!(dot, "    {} -> {};", a, b).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl fmt::Display for RuleAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} distinct rules over {} pages", self.rules.len(), self.pages.len())?;
+        writeln!(f, "shape: {:?}", self.shape)?;
+        if self.is_consistent() {
+            writeln!(f, "transitive closure: consistent")?;
+        } else {
+            writeln!(
+                f,
+                "transitive closure: inconsistent, {} contradicting page pairs",
+                self.contradictions.len()
+            )?;
+        }
+        for component in &self.cycles {
+            writeln!(f, "cycle of {} pages: {}", component.len(), component.iter().join(","))?;
+        }
+        writeln!(f, "{} rules inside cycles", self.cyclic.len())?;
+        write!(f, "{} redundant rules", self.redundant.len())?;
+        if !self.redundant.is_empty() {
+            let shown = self.redundant.iter().take(16).map(|&(a, b)| format!("{}|{}", a, b)).join(" ");
+            write!(f, ": {}", shown)?;
+            if self.redundant.len() > 16 {
+                write!(f, " ...")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// tarjan's algorithm over interned page indices, components come out in reverse topological order
+fn strongly_connected_components(rules: &OrderingRules) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        rules: &'a OrderingRules,
+        next_index: usize,
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        stack: Vec<usize>,
+        on_stack: PageSet,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, page: usize) {
+            self.index[page] = Some(self.next_index);
+            self.low_link[page] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(page);
+            self.on_stack.insert(page);
+
+            for next in self.rules.followers(page) {
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.low_link[page] = self.low_link[page].min(self.low_link[next]);
+                    }
+                    Some(index) if self.on_stack.contains(next) => self.low_link[page] = self.low_link[page].min(index),
+                    Some(_) => {}
+                }
+            }
+
+            if Some(self.low_link[page]) == self.index[page] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member);
+                    if member == page {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let n = rules.interner().len();
+    let mut tarjan = Tarjan {
+        rules,
+        next_index: 0,
+        index: vec![None; n],
+        low_link: vec![0; n],
+        stack: Vec::new(),
+        on_stack: PageSet::new(n),
+        components: Vec::new(),
+    };
+    for page in 0..n {
+        if tarjan.index[page].is_none() {
+            tarjan.visit(page);
+        }
+    }
+    tarjan.components
+}
+
+#[inline]
+pub fn print_analysis(input: &str, sentinel: &SolverSentinel) {
+    let (orderings, _) = parse(input);
+    let analysis = RuleAnalysis::new(&orderings);
+    sentinel_println!(Sentinel: sentinel, println!("day 5 rule analysis:\n{}", analysis));
+}
+
+#[inline]
+pub fn write_dot(input: &str, path: &Path) -> io::Result<()> {
+    let (orderings, _) = parse(input);
+    fs::write(path, RuleAnalysis::new(&orderings).to_dot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+
+    #[test]
+    fn redundant_rules_are_implied_by_other_rules() {
+        assert_eq!(
+            RuleAnalysis::new(&[(1, 2), (2, 3), (1, 3)]).redundant,
+            [(1, 3)],
+            "1|3 follows from 1|2 and 2|3"
+        );
+        // the only way from 1 to 2 other than 1|2 is 1|3, 3|1 and then 1|2 again
+        assert!(RuleAnalysis::new(&[(1, 2), (1, 3), (3, 1)]).redundant.is_empty(), "1|2 is needed");
+        // both rules into 2 leave the same cycle, and nothing else leads out of it
+        let analysis = RuleAnalysis::new(&[(1, 3), (3, 1), (1, 2), (3, 2)]);
+        assert!(analysis.redundant.is_empty(), "the cycle needs a rule into 2");
+        assert_eq!(analysis.cyclic, [(1, 3), (3, 1)], "1|3 and 3|1 make up the cycle");
+        // a path around the cycle on {1, 2} still implies 1|4
+        let analysis = RuleAnalysis::new(&[(1, 2), (2, 1), (2, 3), (3, 4), (1, 4)]);
+        assert_eq!(analysis.redundant, [(1, 4)], "2|3 and 3|4 lead from the cycle to 4");
+        assert_eq!(analysis.cyclic, [(1, 2), (2, 1)], "1|2 and 2|1 make up the cycle");
+        assert_eq!(RuleAnalysis::new(&[(1, 1)]).cyclic, [(1, 1)], "a rule on one page is a cycle of its own");
+    }
+
+    #[test]
+    fn redundant_rules_match_brute_force() {
+        // whether `to` can be reached from `from` over the rules that `keep` lets through
+        fn reaches(rules: &[(Page, Page)], from: Page, to: Page, keep: impl Fn(Page, Page) -> bool) -> bool {
+            let mut reached = vec![from];
+            while let Some(&(_, next)) = rules.iter().find(|&&(a, b)| keep(a, b) && reached.contains(&a) && !reached.contains(&b)) {
+                reached.push(next);
+            }
+            reached.contains(&to)
+        }
+        let mut rng = Xorshift::new();
+        for _ in 0..300 {
+            let pages = rng.below(6) + 2;
+            let rules = (0..rng.below(12))
+                .map(|_| (rng.below(pages) as Page, rng.below(pages) as Page))
+                .collect::<Vec<_>>();
+            let analysis = RuleAnalysis::new(&rules);
+            let same_cycle = |a: Page, b: Page| a == b || (reaches(&rules, a, b, |_, _| true) && reaches(&rules, b, a, |_, _| true));
+            let cyclic = analysis.rules.iter().copied().filter(|&(a, b)| same_cycle(a, b)).collect::<Vec<_>>();
+            // everything reachable from a with every rule from a's cycle to b's dropped
+            let redundant = analysis
+                .rules
+                .iter()
+                .copied()
+                .filter(|&(a, b)| !same_cycle(a, b) && reaches(&rules, a, b, |from, to| !(same_cycle(from, a) && same_cycle(to, b))))
+                .collect::<Vec<_>>();
+            assert_eq!(analysis.cyclic, cyclic, "rules {:?}", rules);
+            assert_eq!(analysis.redundant, redundant, "rules {:?}", rules);
+        }
+    }
+}
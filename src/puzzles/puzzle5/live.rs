@@ -0,0 +1,253 @@
+use super::{parse, Page};
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use ahash::{AHashMap, AHashSet};
+use core::fmt;
+use core::str::FromStr;
+use itertools::Itertools;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RuleEdit {
+    Add(Page, Page),
+    Remove(Page, Page),
+}
+
+impl FromStr for RuleEdit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_rule = |rule: &str| {
+            let (l, r) = rule.split_once('|')?;
+            Some((l.parse::<Page>().ok()?, r.parse::<Page>().ok()?))
+        };
+        let invalid = || format!("Invalid rule edit, expected +a|b or -a|b: {}", s);
+        if let Some(rule) = s.strip_prefix('+') {
+            let (l, r) = parse_rule(rule).ok_or_else(invalid)?;
+            Ok(Self::Add(l, r))
+        } else if let Some(rule) = s.strip_prefix('-') {
+            let (l, r) = parse_rule(rule).ok_or_else(invalid)?;
+            Ok(Self::Remove(l, r))
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl fmt::Display for RuleEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Add(l, r) => write!(f, "+{}|{}", l, r),
+            Self::Remove(l, r) => write!(f, "-{}|{}", l, r),
+        }
+    }
+}
+
+/// Rule set that can change at runtime, keeping the valid updates and their middle-page sum current.
+///
+/// Every update tracks how many rules it breaks. Adding or removing `a|b` only touches the updates that contain
+/// `a`, and only those whose count moves to or from zero change the totals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStore {
+    rules: AHashSet<(Page, Page)>,
+    updates: Vec<Vec<Page>>,
+    // page -> every (update, position) it appears at
+    occurrences: AHashMap<Page, Vec<(usize, usize)>>,
+    positions: Vec<AHashMap<Page, usize>>,
+    broken: Vec<usize>,
+    valid_count: usize,
+    middle_sum: u64,
+}
+
+impl RuleStore {
+    #[inline]
+    pub fn new(orderings: &[(Page, Page)], updates: Vec<Vec<Page>>) -> Self {
+        let mut occurrences: AHashMap<Page, Vec<(usize, usize)>> = AHashMap::new();
+        let mut positions = Vec::with_capacity(updates.len());
+        for (index, update) in updates.iter().enumerate() {
+            let mut update_positions = AHashMap::with_capacity(update.len());
+            for (pos, &page) in update.iter().enumerate() {
+                occurrences.entry(page).or_default().push((index, pos));
+                update_positions.insert(page, pos);
+            }
+            positions.push(update_positions);
+        }
+
+        let mut store = Self {
+            rules: AHashSet::new(),
+            broken: vec![0; updates.len()],
+            valid_count: updates.len(),
+            middle_sum: updates.iter().map(|update| update[update.len() / 2] as u64).sum(),
+            updates,
+            occurrences,
+            positions,
+        };
+        for &(l, r) in orderings {
+            store.add_rule(l, r);
+        }
+        store
+    }
+
+    /// Returns `false` if the rule was already present.
+    #[inline]
+    pub fn add_rule(&mut self, before: Page, after: Page) -> bool {
+        if !self.rules.insert((before, after)) {
+            return false;
+        }
+        for index in self.breaking_updates(before, after) {
+            self.broken[index] += 1;
+            if self.broken[index] == 1 {
+                self.valid_count -= 1;
+                self.middle_sum -= self.middle_page(index) as u64;
+            }
+        }
+        true
+    }
+
+    /// Returns `false` if the rule wasn't present.
+    #[inline]
+    pub fn remove_rule(&mut self, before: Page, after: Page) -> bool {
+        if !self.rules.remove(&(before, after)) {
+            return false;
+        }
+        for index in self.breaking_updates(before, after) {
+            self.broken[index] -= 1;
+            if self.broken[index] == 0 {
+                self.valid_count += 1;
+                self.middle_sum += self.middle_page(index) as u64;
+            }
+        }
+        true
+    }
+
+    #[inline]
+    pub fn apply(&mut self, edit: RuleEdit) -> bool {
+        match edit {
+            RuleEdit::Add(l, r) => self.add_rule(l, r),
+            RuleEdit::Remove(l, r) => self.remove_rule(l, r),
+        }
+    }
+
+    // updates that put `after` somewhere before `before`
+    fn breaking_updates(&self, before: Page, after: Page) -> Vec<usize> {
+        self.occurrences.get(&before).map_or_else(Vec::new, |occurrences| {
+            occurrences
+                .iter()
+                .filter(|&&(index, pos)| self.positions[index].get(&after).is_some_and(|&after_pos| after_pos < pos))
+                .map(|&(index, _)| index)
+                .collect()
+        })
+    }
+
+    fn middle_page(&self, index: usize) -> Page {
+        let update = &self.updates[index];
+        update[update.len() / 2]
+    }
+
+    #[inline]
+    pub fn is_valid(&self, index: usize) -> bool {
+        self.broken[index] == 0
+    }
+
+    #[inline]
+    pub fn valid_updates(&self) -> impl Iterator<Item = &[Page]> + '_ {
+        (0..self.updates.len())
+            .filter(|&index| self.is_valid(index))
+            .map(|index| self.updates[index].as_slice())
+    }
+
+    #[inline]
+    pub const fn valid_count(&self) -> usize {
+        self.valid_count
+    }
+
+    #[inline]
+    pub const fn middle_sum(&self) -> u64 {
+        self.middle_sum
+    }
+}
+
+#[inline]
+pub fn print_edits(input: &str, edits: &[RuleEdit], sentinel: &SolverSentinel) {
+    let (orderings, updates) = parse(input);
+    let mut store = RuleStore::new(&orderings, updates);
+    sentinel_println!(
+        Sentinel: sentinel,
+        println!("day 5: {} valid updates, middle sum {}", store.valid_count(), store.middle_sum())
+    );
+    for &edit in edits {
+        let changed = store.apply(edit);
+        sentinel_println!(
+            Sentinel: sentinel,
+            println!(
+                "day 5: after {}{}: {} valid updates, middle sum {}",
+                edit,
+                if changed { "" } else { " (no change)" },
+                store.valid_count(),
+                store.middle_sum()
+            )
+        );
+    }
+    let first_valid = store.valid_updates().next().map(|update| update.iter().join(","));
+    if let Some(first) = first_valid {
+        sentinel_println!(Sentinel: sentinel, println!("day 5: first valid update is {}", first));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    use super::*;
+    use crate::puzzles::puzzle5::OrderingRules;
+    use crate::puzzles::test_rng::Xorshift;
+    use alloc::collections::BTreeSet;
+
+    #[test]
+    fn edits_match_recomputing_from_scratch() {
+        let mut rng = Xorshift::new();
+        let (mut readded, mut missing) = (0, 0);
+        for _ in 0..50 {
+            // few pages, so edits keep hitting rules that are already there or already gone
+            let updates = (0..=rng.below(20))
+                .map(|_| {
+                    let mut update = (1..=8).collect::<Vec<Page>>();
+                    for i in (1..update.len()).rev() {
+                        update.swap(i, rng.below(i + 1));
+                    }
+                    update.truncate(rng.below(8) + 1);
+                    update
+                })
+                .collect::<Vec<_>>();
+            let mut rules = BTreeSet::new();
+            let mut store = RuleStore::new(&[], updates.clone());
+            for _ in 0..100 {
+                let rule = (rng.below(8) as Page + 1, rng.below(8) as Page + 1);
+                let edit = if rng.below(2) == 0 {
+                    RuleEdit::Add(rule.0, rule.1)
+                } else {
+                    RuleEdit::Remove(rule.0, rule.1)
+                };
+                let changed = match edit {
+                    RuleEdit::Add(..) => rules.insert(rule),
+                    RuleEdit::Remove(..) => rules.remove(&rule),
+                };
+                match (edit, changed) {
+                    (RuleEdit::Add(..), false) => readded += 1,
+                    (RuleEdit::Remove(..), false) => missing += 1,
+                    _ => {}
+                }
+                assert_eq!(store.apply(edit), changed, "{} on {:?}", edit, rules);
+
+                let recomputed = OrderingRules::new(&rules.iter().copied().collect::<Vec<_>>());
+                let valid = updates.iter().filter(|update| recomputed.is_valid(update)).collect::<Vec<_>>();
+                let middle_sum = valid.iter().map(|update| update[update.len() / 2] as u64).sum::<u64>();
+                assert_eq!(store.valid_count(), valid.len(), "after {} on {:?}", edit, rules);
+                assert_eq!(store.middle_sum(), middle_sum, "after {} on {:?}", edit, rules);
+            }
+        }
+        assert!(
+            readded > 0 && missing > 0,
+            "edits re-added {} rules and removed {} missing ones",
+            readded,
+            missing
+        );
+    }
+}
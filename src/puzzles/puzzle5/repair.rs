@@ -0,0 +1,269 @@
+extern crate alloc;
+use super::{parse, reorder, CycleError, OrderingRules, Page};
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use alloc::collections::BTreeMap;
+use core::fmt;
+use itertools::Itertools;
+
+/// A valid ordering of an update together with what it costs to get there from the original.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Repair {
+    pub ordering: Vec<Page>,
+    pub cost: usize,
+}
+
+// Both searches walk the downsets of the update's rules: a set of pages that can open the repaired ordering.
+// Any page whose predecessors are all placed can go next, so every valid ordering is a path from the empty set
+// to the full one. With the puzzle's totally ordered updates there is exactly one such path; sparser rules can
+// blow up to 2^n sets, so the searches give up once a single step holds more than `MAX_LAYER` of them.
+const MAX_LAYER: usize = 1 << 16;
+
+/// Why an update has no repair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairError {
+    Cycle(CycleError),
+    /// More than 64 pages, or rules too loose to search every ordering they allow.
+    TooLarge(Vec<Page>),
+}
+
+impl From<CycleError> for RepairError {
+    fn from(err: CycleError) -> Self {
+        Self::Cycle(err)
+    }
+}
+
+impl fmt::Display for RepairError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Cycle(ref err) => write!(f, "{}", err),
+            Self::TooLarge(ref update) => write!(f, "update {} allows too many orderings to search for a repair", update.iter().join(",")),
+        }
+    }
+}
+
+/// An invalid update with its repairs under both distance measures.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RankedUpdate {
+    pub update: Vec<Page>,
+    pub swaps: Repair,
+    pub moves: Repair,
+}
+
+/// `preds[i]` has bit `j` set when `update[j]` must come before `update[i]`.
+fn predecessor_masks(update: &[Page], rules: &OrderingRules) -> Result<Vec<u64>, RepairError> {
+    if update.len() > 64 {
+        return Err(RepairError::TooLarge(update.to_vec()));
+    }
+    Ok(update
+        .iter()
+        .map(|&page| {
+            update
+                .iter()
+                .enumerate()
+                .filter(|&(_, &earlier)| rules.must_precede(earlier, page))
+                .fold(0, |mask, (j, _)| mask | 1 << j)
+        })
+        .collect())
+}
+
+/// Valid ordering reachable with the fewest swaps of neighbouring pages.
+///
+/// Each placed page costs one swap per page that stood before it in the original but is still unplaced,
+/// which adds up to the number of pairs whose order changes.
+#[inline]
+pub fn min_adjacent_swaps(update: &[Page], rules: &OrderingRules) -> Result<Repair, RepairError> {
+    reorder(update, rules)?;
+    let preds = predecessor_masks(update, rules)?;
+
+    let mut layer = BTreeMap::from([(0_u64, 0_usize)]);
+    let mut parent = BTreeMap::new();
+    for _ in 0..update.len() {
+        let mut next = BTreeMap::new();
+        for (&mask, &cost) in &layer {
+            for (i, &pred) in preds.iter().enumerate() {
+                if mask & 1 << i != 0 || pred & !mask != 0 {
+                    continue;
+                }
+                let cost = cost + (!mask & ((1 << i) - 1)).count_ones() as usize;
+                let placed = mask | 1 << i;
+                if next.get(&placed).is_none_or(|&best| cost < best) {
+                    next.insert(placed, cost);
+                    parent.insert(placed, (mask, i));
+                }
+            }
+        }
+        if next.len() > MAX_LAYER {
+            return Err(RepairError::TooLarge(update.to_vec()));
+        }
+        layer = next;
+    }
+
+    let (&full, &cost) = layer.iter().next().unwrap();
+    let mut ordering = Vec::with_capacity(update.len());
+    let mut mask = full;
+    while let Some(&(prev, i)) = parent.get(&mask) {
+        ordering.push(update[i]);
+        mask = prev;
+    }
+    ordering.reverse();
+    Ok(Repair { ordering, cost })
+}
+
+/// Valid ordering reachable by taking the fewest pages out and reinserting them elsewhere.
+///
+/// The pages left in place keep their original relative order, so this maximizes the number of pages that can be
+/// placed at increasing original positions.
+#[inline]
+pub fn min_moves(update: &[Page], rules: &OrderingRules) -> Result<Repair, RepairError> {
+    reorder(update, rules)?;
+    let preds = predecessor_masks(update, rules)?;
+
+    // (placed mask, 1 + original index of the last page kept in place) -> most pages kept in place
+    let mut layer = BTreeMap::from([((0_u64, 0_usize), 0_usize)]);
+    let mut parent = BTreeMap::new();
+    for _ in 0..update.len() {
+        let mut next = BTreeMap::new();
+        for (&(mask, last), &kept) in &layer {
+            for (i, &pred) in preds.iter().enumerate() {
+                if mask & 1 << i != 0 || pred & !mask != 0 {
+                    continue;
+                }
+                // the page can always be moved, and stay in place only if it comes after the last page that did
+                let stay = (i + 1 > last).then_some(((mask | 1 << i, i + 1), kept + 1));
+                for (state, kept) in [Some(((mask | 1 << i, last), kept)), stay].into_iter().flatten() {
+                    if next.get(&state).is_none_or(|&best| kept > best) {
+                        next.insert(state, kept);
+                        parent.insert(state, ((mask, last), i));
+                    }
+                }
+            }
+        }
+        if next.len() > MAX_LAYER {
+            return Err(RepairError::TooLarge(update.to_vec()));
+        }
+        layer = next;
+    }
+
+    let (&best, &kept) = layer.iter().max_by_key(|&(_, &kept)| kept).unwrap();
+    let mut ordering = Vec::with_capacity(update.len());
+    let mut state = best;
+    while let Some(&(prev, i)) = parent.get(&state) {
+        ordering.push(update[i]);
+        state = prev;
+    }
+    ordering.reverse();
+    Ok(Repair {
+        ordering,
+        cost: update.len() - kept,
+    })
+}
+
+/// Invalid updates of `input`, worst first; updates that can't be repaired, because their rules are cyclic or they
+/// are too large to search, come first.
+#[inline]
+pub fn rank_invalid_updates(input: &str) -> Vec<Result<RankedUpdate, RepairError>> {
+    let (orderings, updates) = parse(input);
+    let rules = OrderingRules::new(&orderings);
+
+    updates
+        .into_iter()
+        .filter(|update| !rules.is_valid(update))
+        .map(|update| {
+            let swaps = min_adjacent_swaps(&update, &rules)?;
+            let moves = min_moves(&update, &rules)?;
+            Ok(RankedUpdate { update, swaps, moves })
+        })
+        .sorted_by_key(|ranked| ranked.as_ref().map_or((usize::MAX, usize::MAX), |r| (r.swaps.cost, r.moves.cost)))
+        .rev()
+        .collect()
+}
+
+#[inline]
+pub fn print_ranking(input: &str, sentinel: &SolverSentinel) {
+    for ranked in rank_invalid_updates(input) {
+        match ranked {
+            Ok(ranked) => sentinel_println!(
+                Sentinel: sentinel,
+                println!(
+                    "day 5: {} needs {} swaps or {} moves -> {}",
+                    ranked.update.iter().join(","),
+                    ranked.swaps.cost,
+                    ranked.moves.cost,
+                    ranked.swaps.ordering.iter().join(",")
+                )
+            ),
+            Err(err) => sentinel_println!(Sentinel: sentinel, println!("day 5: {}", err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+
+    #[test]
+    fn costs_match_inversions_and_longest_increasing_run() {
+        let mut rng = Xorshift::new();
+        for _ in 0..300 {
+            // pages ordered by their value, so the only valid ordering is the sorted one
+            let n = rng.below(12) + 1;
+            let mut update = (10..10 + n as Page).collect::<Vec<_>>();
+            for i in (1..n).rev() {
+                update.swap(i, rng.below(i + 1));
+            }
+            let orderings = update
+                .iter()
+                .copied()
+                .tuple_combinations()
+                .map(|(a, b)| (a.min(b), a.max(b)))
+                .collect::<Vec<_>>();
+            let rules = OrderingRules::new(&orderings);
+            let sorted = update.iter().copied().sorted().collect::<Vec<_>>();
+
+            let inversions = update.iter().tuple_combinations().filter(|&(a, b)| a > b).count();
+            // longest[i] is the longest increasing run of pages ending at update[i]
+            let mut longest = vec![1; n];
+            for i in 0..n {
+                longest[i] = (0..i).filter(|&j| update[j] < update[i]).map(|j| longest[j] + 1).max().unwrap_or(1);
+            }
+            let run = longest.iter().copied().max().unwrap_or(0);
+
+            let swaps = min_adjacent_swaps(&update, &rules).unwrap();
+            let moves = min_moves(&update, &rules).unwrap();
+            assert_eq!(
+                (swaps.ordering.as_slice(), swaps.cost),
+                (sorted.as_slice(), inversions),
+                "swaps for {:?}",
+                update
+            );
+            assert_eq!(
+                (moves.ordering.as_slice(), moves.cost),
+                (sorted.as_slice(), n - run),
+                "moves for {:?}",
+                update
+            );
+        }
+    }
+
+    #[test]
+    fn too_large_updates_are_errors() {
+        let rules = OrderingRules::new(&[]);
+        for update in [(0..65).collect::<Vec<Page>>(), (0..30).collect()] {
+            let err = RepairError::TooLarge(update.clone());
+            assert_eq!(min_adjacent_swaps(&update, &rules), Err(err.clone()), "{} pages", update.len());
+            assert_eq!(min_moves(&update, &rules), Err(err), "{} pages", update.len());
+        }
+    }
+
+    #[test]
+    fn rankings_keep_updates_too_large_to_repair() {
+        let large = (0..65).join(",");
+        let ranking = rank_invalid_updates(&format!("1|0\n\n{}\n1,0,2\n0,1,2\n", large));
+        let expected_large = Err(RepairError::TooLarge((0..65).collect()));
+        assert_eq!(ranking.len(), 2, "both invalid updates are ranked");
+        assert_eq!(ranking[0], expected_large, "the update too large to search comes first");
+        assert!(ranking[1].is_ok(), "0,1,2 is repaired by one swap");
+    }
+}
@@ -1,19 +1,38 @@
+use crate::grid::{Direction, Grid, RaggedRow};
+use crate::sentinel_println;
 use crate::solver::SolverSentinel;
 use core::fmt;
-use core::fmt::Debug;
-use core::mem;
+use core::fmt::{Debug, Write as _};
+use core::str::FromStr;
+use itertools::Itertools;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+pub mod bench;
+pub mod guards;
+pub mod heatmap;
+mod jump;
+pub mod render;
+mod sparse;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct NavigatableMap {
-    tiles: Vec2D<Tile>,
-    visited_tiles: Vec2D<VisitedDirections>,
+    tiles: Grid<PackedTile>,
+    // visits stamped with any other generation don't count
+    generation: u16,
     patroller: Patroller,
+    // every guard on the map, `patroller` starts out as the first
+    guards: Vec<Patroller>,
+    turn_policy: TurnPolicy,
     w: usize,
     h: usize,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Patroller {
     pos: (usize, usize),
     dir: Direction,
+    // whether an odd number of turns was taken, the alternating policy turns left next
+    odd_turns: bool,
 }
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Ternary {
@@ -40,53 +59,271 @@ impl Ternary {
         }
     }
 }
+/// Why a day 6 map couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MapError {
+    NoGuard,
+    MultipleGuards(Vec<(usize, usize)>),
+    InvalidTile { pos: (usize, usize), byte: u8 },
+    RaggedRow { y: usize, len: usize, expected: usize },
+}
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NoGuard => write!(f, "the map has no guard, expected one of ^ > v <"),
+            Self::MultipleGuards(ref positions) => write!(
+                f,
+                "the map has {} guards, at {}",
+                positions.len(),
+                positions.iter().map(|pos| format!("{:?}", pos)).join(", ")
+            ),
+            Self::InvalidTile { pos, byte } => write!(f, "invalid tile {:?} at {:?}", byte as char, pos),
+            Self::RaggedRow { y, len, expected } => write!(f, "{}", RaggedRow { y, len, expected }),
+        }
+    }
+}
+impl From<RaggedRow> for MapError {
+    fn from(RaggedRow { y, len, expected }: RaggedRow) -> Self {
+        Self::RaggedRow { y, len, expected }
+    }
+}
+impl FromStr for NavigatableMap {
+    type Err = MapError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let map = Self::with_guards(input)?;
+        if map.guards.len() > 1 {
+            return Err(MapError::MultipleGuards(map.guards.into_iter().map(|guard| guard.pos).collect()));
+        }
+        Ok(map)
+    }
+}
 impl NavigatableMap {
     #[inline]
     pub fn new(input: &str) -> Self {
-        let w = input.lines().next().unwrap().len();
-        let h = input.lines().count();
-        let tiles: Vec<Tile> = input
-            .lines()
-            .map(|l| l.trim())
-            .collect::<String>()
-            .bytes()
-            .map(|a| Tile::try_from(a).unwrap())
-            .collect();
-        let patroller_pos = {
-            // find the index of the first `Patroller` tile in `tiles`
-            // return the 2D coordinates
-            let (index, _) = input
-                .lines()
-                .map(|l| l.trim())
-                .collect::<String>()
-                .bytes()
-                .enumerate()
-                .find(|&(_, byte)| byte == b'^')
-                .unwrap();
-            (index % w, index / w)
-        };
-        let visited_tiles = vec![VisitedDirections::new(); w * h];
-        // todo fix fucking logic
-        // visited_tiles[patroller_pos.1 * w + patroller_pos.0].set_direction(Direction::Up);
-        Self {
-            tiles: Vec2D::new(w, h, tiles),
-            visited_tiles: Vec2D::new(w, h, visited_tiles),
-            patroller: Patroller {
-                pos: patroller_pos,
-                dir: Direction::Up,
-            },
+        input.parse().unwrap_or_else(
+            #[cold]
+            |err| panic!("Invalid day 6 map: {}", err),
+        )
+    }
+    /// Reads a map with any number of guards, at least one. Use [`str::parse`] for maps that must have exactly one.
+    #[inline]
+    pub fn with_guards(input: &str) -> Result<Self, MapError> {
+        let mut guards = Vec::new();
+        let tiles = Grid::parse(input, |pos, byte| {
+            if let Some(dir) = Direction::from_arrow(byte) {
+                guards.push(Patroller { pos, dir, odd_turns: false });
+            }
+            Ok::<_, MapError>(PackedTile::new(Tile::try_from(byte).map_err(|_| MapError::InvalidTile { pos, byte })?))
+        })?;
+        let (w, h) = (tiles.width(), tiles.height());
+        Ok(Self {
+            tiles,
+            generation: 0,
+            patroller: guards.first().ok_or(MapError::NoGuard)?.clone(),
+            guards,
+            turn_policy: TurnPolicy::default(),
             w,
             h,
+        })
+    }
+    #[inline]
+    #[must_use]
+    pub const fn with_turn_policy(mut self, turn_policy: TurnPolicy) -> Self {
+        self.turn_policy = turn_policy;
+        self
+    }
+    /// Records the unobstructed walk from the patroller's start until it leaves the map or enters a loop.
+    #[inline]
+    pub fn patrol(&self) -> PatrolPath {
+        self.patrol_from(&self.patroller)
+    }
+    /// Same as [`Self::patrol`] for a patroller starting out as `start`.
+    #[inline]
+    pub fn patrol_from(&self, start: &Patroller) -> PatrolPath {
+        assert!(
+            start.pos.0 < self.w && start.pos.1 < self.h,
+            "Patroller starts outside the map at {:?}",
+            start.pos
+        );
+        let mut walker = self.clone();
+        walker.patroller.clone_from(start);
+        let mut entered = vec![false; self.w * self.h];
+        let mut path = PatrolPath {
+            steps: vec![start.clone()],
+            turns: Vec::new(),
+            first_visits: vec![0],
+            end: PatrolEnd::Exit(start.clone()),
+        };
+        entered[start.pos.1 * self.w + start.pos.0] = true;
+        loop {
+            let before = walker.patroller.clone();
+            match walker.step() {
+                HasFinished::Unfinished => {}
+                HasFinished::OutOfBounds => {
+                    path.end = PatrolEnd::Exit(before);
+                    return path;
+                }
+                HasFinished::LoopEntered => {
+                    let entry = path.steps.iter().position(|step| *step == walker.patroller).unwrap();
+                    path.end = PatrolEnd::Loop { entry };
+                    return path;
+                }
+            }
+            let (x, y) = walker.patroller.pos;
+            if walker.patroller.pos == before.pos {
+                path.turns.push(path.steps.len() - 1);
+            } else if !entered[y * self.w + x] {
+                entered[y * self.w + x] = true;
+                path.first_visits.push(path.steps.len());
+            }
+            path.steps.push(walker.patroller.clone());
+        }
+    }
+    /// Tiles of the unobstructed walk in first-visit order, each paired with the patroller as it stood just before
+    /// stepping onto it. The start tile is left out, nothing can be placed there.
+    ///
+    /// An obstacle on a path tile leaves the walk unchanged up to that first step, so these are the candidates part 2
+    /// has to simulate and where each simulation can start. The rest are [`Self::unreached_traps`].
+    #[inline]
+    pub fn first_entries(&self) -> Vec<((usize, usize), Patroller)> {
+        let path = self.patrol();
+        path.first_visits[1..]
+            .iter()
+            .map(|&index| (path.steps[index].pos, path.steps[index - 1].clone()))
+            .collect()
+    }
+    /// Air tiles the unobstructed walk never reaches, row by row, if that walk loops by itself. An obstacle there
+    /// leaves the walk as it is, so each of them traps the patroller too; when the walk leaves the map none of them do.
+    #[inline]
+    pub fn unreached_traps(&self) -> Vec<(usize, usize)> {
+        let path = self.patrol();
+        if let PatrolEnd::Exit(_) = *path.end() {
+            return Vec::new();
+        }
+        let mut reached = Grid::from_fn(self.w, self.h, |_| false);
+        for pos in path.tiles() {
+            reached[pos] = true;
+        }
+        (0..self.h)
+            .flat_map(|y| (0..self.w).map(move |x| (x, y)))
+            .filter(|&(x, y)| !reached[(x, y)] && self.tile_at(x, y) == Tile::Air)
+            .collect()
+    }
+    /// Copy of the untouched map with an extra obstacle at `(x, y)`.
+    #[inline]
+    pub fn with_obstacle(&self, x: usize, y: usize) -> Self {
+        let mut blocked = self.clone();
+        blocked.set_tile(x, y, Tile::Obstacle);
+        blocked
+    }
+    /// The loop the patroller ends up in with an extra obstacle at `(x, y)`, `None` if it leaves the map.
+    #[inline]
+    pub fn looping_obstacle_at(&self, x: usize, y: usize) -> Option<LoopingObstacle> {
+        let path = self.with_obstacle(x, y).patrol();
+        match *path.end() {
+            PatrolEnd::Exit(_) => None,
+            PatrolEnd::Loop { entry } => Some(LoopingObstacle {
+                obstacle: (x, y),
+                entry,
+                length: path.steps().len() - entry,
+                tiles: path.steps()[entry..].iter().map(|step| step.pos).unique().collect(),
+            }),
+        }
+    }
+    /// Every obstacle position that traps the patroller, in the order its tile is first reached on the unobstructed walk
+    /// followed by the [`Self::unreached_traps`].
+    #[inline]
+    pub fn looping_obstacles(&self) -> Vec<LoopingObstacle> {
+        self.first_entries()
+            .into_iter()
+            .map(|entry| entry.0)
+            .chain(self.unreached_traps())
+            .filter_map(|(x, y)| self.looping_obstacle_at(x, y))
+            .collect()
+    }
+    /// Walks from `start` with an extra obstacle at `(x, y)`, then forgets the walk's visits.
+    #[inline]
+    pub fn loops_with_obstacle_at(&mut self, x: usize, y: usize, start: &Patroller) -> bool {
+        let loops = self.walk_with_obstacle_at(x, y, start);
+        self.reset_visits();
+        loops
+    }
+    /// Walks from `start` with an extra obstacle at `(x, y)` and takes the obstacle away again. The visits of the walk
+    /// stay recorded.
+    #[inline]
+    pub fn walk_with_obstacle_at(&mut self, x: usize, y: usize, start: &Patroller) -> bool {
+        assert!(
+            start.pos.0 < self.w && start.pos.1 < self.h,
+            "Patroller starts outside the map at {:?}",
+            start.pos
+        );
+        self.patroller.clone_from(start);
+        let previous_tile = self.tile_at(x, y);
+        self.set_tile(x, y, Tile::Obstacle);
+        // the same walk as repeated `step` calls, but looking each tile up only once per step
+        let (generation, turn_policy) = (self.generation, self.turn_policy);
+        let patroller = &mut self.patroller;
+        let loops = loop {
+            let (x, y) = patroller.pos;
+            let tile = &mut self.tiles[(x, y)];
+            if tile.visits(generation, patroller.odd_turns).is_direction_visited(patroller.dir) {
+                break true;
+            }
+            let Some(ahead) = patroller.dir.offset_bounded(x, y, self.w, self.h) else {
+                break false;
+            };
+            tile.visit(patroller.dir, patroller.odd_turns, generation);
+            if self.tiles[ahead].tile() == Tile::Obstacle {
+                turn_policy.turn(patroller);
+            } else {
+                patroller.pos = ahead;
+            }
+        };
+        self.set_tile(x, y, previous_tile);
+        loops
+    }
+    #[inline]
+    pub fn tile_at(&self, x: usize, y: usize) -> Tile {
+        self.tiles[(x, y)].tile()
+    }
+    #[inline]
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: Tile) {
+        self.tiles[(x, y)].set_tile(tile);
+    }
+    /// Every direction the tile was left in since the visits were last forgotten.
+    #[inline]
+    pub fn visits_at(&self, x: usize, y: usize) -> VisitedDirections {
+        self.tiles[(x, y)].trail(self.generation)
+    }
+    #[inline]
+    pub fn record_visit(&mut self, x: usize, y: usize, dir: Direction, odd_turns: bool) {
+        let generation = self.generation;
+        self.tiles[(x, y)].visit(dir, odd_turns, generation);
+    }
+    /// Forgets every visit by moving on to the next generation. The tiles are only rewritten when the generation
+    /// stamps run out.
+    #[inline]
+    pub fn reset_visits(&mut self) {
+        self.generation = (self.generation + 1) % PackedTile::GENERATIONS;
+        if self.generation == 0 {
+            self.clear_visits();
         }
     }
+    /// Forgets every visit by rewriting every tile.
     #[inline]
-    pub fn step(&mut self) -> Option<HasFinished> {
+    pub fn clear_visits(&mut self) {
+        for tile in self.tiles.iter_mut() {
+            tile.clear_visits();
+        }
+    }
+    #[inline]
+    pub fn step(&mut self) -> HasFinished {
         // eprintln!("step");
         if self.check_whether_patroller_visited_current_tile_with_same_direction_before() {
             // eprintln!("step: patroller visited current tile with same direction before");
-            return Some(HasFinished::LoopEntered);
+            return HasFinished::LoopEntered;
         }
-        match self.is_tile_ahead_an_obstacle()?.as_bool() {
+        match self.is_tile_ahead_an_obstacle().as_bool() {
             Some(true) => {
                 // eprintln!("step: tile ahead is an obstacle");
                 self.rotate_patroller();
@@ -97,50 +334,27 @@ impl NavigatableMap {
             }
 
             None => {
-                return Some(HasFinished::OutOfBounds);
+                return HasFinished::OutOfBounds;
             }
         }
         // eprintln!("step: unfinished");
-        Some(HasFinished::Unfinished)
+        HasFinished::Unfinished
     }
     #[inline]
-    pub fn is_tile_ahead_an_obstacle(&self) -> Option<Ternary> {
+    pub fn is_tile_ahead_an_obstacle(&self) -> Ternary {
         // eprintln!("checking whether tile ahead is an obstacle");
         let (x, y) = self.patroller.pos;
         // eprintln!("{} {}, {} {}", x, y, self.w, self.h);
-        match self.patroller.dir.offset_bounded(x, y, self.w, self.h) {
-            None => Some(Ternary::Unknown),
-            Some((x, y)) => Some((self.tiles.get(x, y)? == &Tile::Obstacle).into()),
-        }
+        let (dx, dy) = self.patroller.dir.delta();
+        self.tiles
+            .get_signed(x as isize + dx, y as isize + dy)
+            .map_or(Ternary::Unknown, |tile| (tile.tile() == Tile::Obstacle).into())
     }
     #[inline]
     pub fn rotate_patroller(&mut self) {
-        let old_dir = self.patroller.dir;
-        self.patroller.dir.rotate_clockwise_inplace();
-        self.get_visited_directions_at_patroller_position_mut().set_direction(old_dir);
-    }
-    #[inline]
-    pub fn get_visited_directions_at_patroller_position_mut(&mut self) -> &mut VisitedDirections {
-        let (x, y) = self.patroller.pos;
-        self.visited_tiles.get_mut(x, y).unwrap_or_else(
-            #[cold]
-            || panic!("Patroller out of bounds"),
-        )
-    }
-    #[inline]
-    pub fn get_visited_directions_at_position(&mut self, x: usize, y: usize) -> &mut VisitedDirections {
-        self.visited_tiles.get_mut(x, y).unwrap_or_else(
-            #[cold]
-            || panic!("Position out of bounds"),
-        )
-    }
-    #[inline]
-    pub fn get_visited_directions_at_patroller_position(&self) -> &VisitedDirections {
-        let (x, y) = self.patroller.pos;
-        self.visited_tiles.get(x, y).unwrap_or_else(
-            #[cold]
-            || panic!("Patroller out of bounds"),
-        )
+        let old = self.patroller.clone();
+        self.turn_policy.turn(&mut self.patroller);
+        self.record_visit(old.pos.0, old.pos.1, old.dir, old.odd_turns);
     }
     #[inline]
     pub fn move_patroller(&mut self) -> Option<()> {
@@ -156,207 +370,257 @@ impl NavigatableMap {
         self.patroller.pos = (nx, ny);
         let dir = self.patroller.dir;
         // eprintln!("Add direction {:?} to visited directions at {}, {}", dir, x, y);
-        self.get_visited_directions_at_position(x, y).set_direction(dir);
+        self.record_visit(x, y, dir, self.patroller.odd_turns);
         Some(())
     }
     #[inline]
     pub fn check_whether_patroller_visited_current_tile_with_same_direction_before(&self) -> bool {
         // eprintln!("checking whether patroller visited current tile with same direction before");
-        let visited = self.get_visited_directions_at_patroller_position();
-        visited.is_direction_visited(self.patroller.dir)
+        // With the alternating policy the same tile and heading can come back with the other turn up next, so the
+        // patroller is only back where it was if the parity of its turns matches too.
+        let (x, y) = self.patroller.pos;
+        self.tiles[(x, y)]
+            .visits(self.generation, self.patroller.odd_turns)
+            .is_direction_visited(self.patroller.dir)
     }
 }
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-enum HasFinished {
-    LoopEntered,
-    OutOfBounds,
-    Unfinished,
+/// An extra obstacle that traps the patroller, with the loop it ends up walking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LoopingObstacle {
+    pub obstacle: (usize, usize),
+    /// Step of the obstructed walk at which the loop is first entered.
+    pub entry: usize,
+    /// Steps per round, turns in place included.
+    pub length: usize,
+    /// Distinct tiles of the loop in the order it visits them.
+    pub tiles: Vec<(usize, usize)>,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Vec2D<T> {
-    data: Vec<T>,
-    w: usize,
-    h: usize,
+impl fmt::Display for LoopingObstacle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "an obstacle at {:?} traps the guard at step {} in a loop of {} steps over {} tiles",
+            self.obstacle,
+            self.entry,
+            self.length,
+            self.tiles.len()
+        )
+    }
+}
+/// How a recorded patrol ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatrolEnd {
+    /// The patroller left the map, this is where it stood before stepping off.
+    Exit(Patroller),
+    /// The patroller came back to `steps[entry]` and would repeat the walk from there forever.
+    Loop { entry: usize },
+}
+/// The patroller's walk as recorded by [`NavigatableMap::patrol`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PatrolPath {
+    // every state in order starting with the start, a turn adds a second state on the same tile
+    steps: Vec<Patroller>,
+    // indices into `steps` of the states the patroller turned from
+    turns: Vec<usize>,
+    // indices into `steps` where a tile was entered for the first time
+    first_visits: Vec<usize>,
+    end: PatrolEnd,
 }
-impl<T> Vec2D<T> {
+impl PatrolPath {
     #[inline]
-    pub fn new(w: usize, h: usize, data: Vec<T>) -> Self {
-        assert!(w * h == data.len(), "Invalid dimensions");
-        Self { data, w, h }
+    pub fn steps(&self) -> &[Patroller] {
+        &self.steps
     }
     #[inline]
-    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
-        assert!(!(x >= self.w || y >= self.h), "Out of bounds");
-        self.data.get(y * self.w + x)
+    pub fn turn_points(&self) -> impl Iterator<Item = &Patroller> + '_ {
+        self.turns.iter().map(|&index| &self.steps[index])
     }
+    /// Distinct tiles in first-visit order, the start included.
     #[inline]
-    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut T> {
-        assert!(!(x >= self.w || y >= self.h), "Out of bounds");
-        self.data.get_mut(y * self.w + x)
+    pub fn tiles(&self) -> impl ExactSizeIterator<Item = (usize, usize)> + '_ {
+        self.first_visits.iter().map(|&index| self.steps[index].pos)
     }
     #[inline]
-    pub fn str<F, R>(&self, f: F) -> String
-    where
-        R: AsRef<str>,
-        F: Fn(&T) -> R,
-    {
-        let mut s = String::with_capacity(self.w * self.h);
-        for y in 0..self.h {
-            for x in 0..self.w {
-                s.push_str(f(self.get(x, y).unwrap()).as_ref());
-            }
-            s.push('\n');
+    pub const fn end(&self) -> &PatrolEnd {
+        &self.end
+    }
+    /// Where the patroller is after `tick` steps, going round its loop again once the recording runs out.
+    /// `None` once it has left the map.
+    #[inline]
+    pub fn position_at(&self, tick: usize) -> Option<&Patroller> {
+        match self.end {
+            _ if tick < self.steps.len() => Some(&self.steps[tick]),
+            PatrolEnd::Exit(_) => None,
+            PatrolEnd::Loop { entry } => Some(&self.steps[entry + (tick - entry) % (self.steps.len() - entry)]),
         }
-        s
     }
 }
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-enum Direction {
-    Up,
+impl fmt::Display for PatrolPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "takes {} steps, turns {} times, covers {} tiles and ",
+            self.steps.len() - 1,
+            self.turns.len(),
+            self.first_visits.len()
+        )?;
+        match self.end {
+            PatrolEnd::Exit(ref last) => write!(f, "leaves the map from {:?} heading {:?}", last.pos, last.dir),
+            PatrolEnd::Loop { entry } => write!(
+                f,
+                "loops back to {:?} heading {:?} after step {}",
+                self.steps[entry].pos, self.steps[entry].dir, entry
+            ),
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum HasFinished {
+    LoopEntered,
+    OutOfBounds,
+    Unfinished,
+}
+/// What the patroller does when it faces an obstacle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TurnPolicy {
+    #[default]
     Right,
-    Down,
     Left,
+    /// Right, then left, then right again, and so on.
+    Alternate,
+    UTurn,
 }
-impl Direction {
-    #[inline]
-    pub fn offset(self, x: usize, y: usize) -> Option<(usize, usize)> {
-        Some(match self {
-            Self::Up => (x, y.checked_sub(1)?),
-            Self::Left => (x.checked_sub(1)?, y),
-            Self::Right => (x.checked_add(1)?, y),
-            Self::Down => (x, y.checked_add(1)?),
-        })
-    }
-    #[inline]
-    pub fn offset_bounded(self, x: usize, y: usize, bound_x: usize, bound_y: usize) -> Option<(usize, usize)> {
-        let r = match self {
-            Self::Up => (x, y.checked_sub(1)?),
-            Self::Left => (x.checked_sub(1)?, y),
-            Self::Right => (x.checked_add(1)?, y),
-            Self::Down => (x, y.checked_add(1)?),
-        };
-        if r.0 >= bound_x || r.1 >= bound_y {
-            None
-        } else {
-            Some(r)
+impl FromStr for TurnPolicy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "right" => Ok(Self::Right),
+            "left" => Ok(Self::Left),
+            "alternate" => Ok(Self::Alternate),
+            "u-turn" => Ok(Self::UTurn),
+            _ => Err(format!("Unknown turn policy, expected right, left, alternate or u-turn: {}", s)),
         }
     }
-    #[inline]
-    pub fn offset_times(self, x: usize, y: usize, factor: usize) -> Option<(usize, usize)> {
-        Some(match self {
-            Self::Up => (x, y.checked_sub(factor)?),
-            Self::Left => (x.checked_sub(factor)?, y),
-            Self::Right => (x.checked_add(factor)?, y),
-            Self::Down => (x, y.checked_add(factor)?),
-        })
-    }
-    #[inline]
-    pub const fn rotate_clockwise(self) -> Self {
+}
+impl TurnPolicy {
+    const fn turn(self, patroller: &mut Patroller) {
         match self {
-            Self::Up => Self::Right,
-            Self::Right => Self::Down,
-            Self::Down => Self::Left,
-            Self::Left => Self::Up,
+            Self::Left => patroller.dir.rotate_counter_clockwise_inplace(),
+            Self::Alternate if patroller.odd_turns => patroller.dir.rotate_counter_clockwise_inplace(),
+            Self::Right | Self::Alternate => patroller.dir.rotate_clockwise_inplace(),
+            Self::UTurn => patroller.dir = patroller.dir.reversed(),
         }
-    }
-    #[inline]
-    pub const fn rotate_counter_clockwise(self) -> Self {
-        match self {
-            Self::Up => Self::Left,
-            Self::Left => Self::Down,
-            Self::Down => Self::Right,
-            Self::Right => Self::Up,
+        if matches!(self, Self::Alternate) {
+            patroller.odd_turns = !patroller.odd_turns;
         }
     }
-    #[inline]
-    pub const fn rotate_clockwise_inplace(&mut self) {
-        *self = self.rotate_clockwise();
-    }
-    #[inline]
-    pub const fn rotate_counter_clockwise_inplace(&mut self) {
-        *self = self.rotate_counter_clockwise();
-    }
 }
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 enum Tile {
     Obstacle,
     Air,
 }
-#[allow(clippy::struct_excessive_bools)] // cry about it
-#[derive(Clone, Hash, Eq, PartialEq)]
-struct VisitedDirections {
-    up: bool,
-    right: bool,
-    down: bool,
-    left: bool,
-}
+/// The directions a tile was left in, one bit per [`Direction`].
+#[derive(Clone, Copy, Hash, Eq, PartialEq)]
+struct VisitedDirections(u8);
 impl Debug for VisitedDirections {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}{}{}{}",
-            if self.up { '^' } else { '_' },
-            if self.right { '>' } else { '_' },
-            if self.down { 'v' } else { '_' },
-            if self.left { '<' } else { '_' }
-        )
+        for dir in Direction::CARDINALS {
+            f.write_char(if self.is_direction_visited(dir) { dir.arrow() } else { '_' })?;
+        }
+        Ok(())
     }
 }
 impl VisitedDirections {
     #[inline]
-    pub const fn new() -> Self {
-        Self {
-            up: false,
-            right: false,
-            down: false,
-            left: false,
-        }
+    pub const fn is_direction_visited(self, dir: Direction) -> bool {
+        self.0 & (1 << dir as u8) != 0
     }
+    #[allow(dead_code)]
     #[inline]
-    pub const fn set_up(&mut self) -> &mut Self {
-        self.up = true;
-        self
+    pub const fn is_all_visited(self) -> bool {
+        self.0 == 0b1111
     }
+    /// One character for the directions the tile was left in, `None` if it wasn't.
     #[inline]
-    pub const fn set_right(&mut self) -> &mut Self {
-        self.right = true;
-        self
+    pub const fn glyph(self) -> Option<char> {
+        let (up, right) = (self.is_direction_visited(Direction::Up), self.is_direction_visited(Direction::Right));
+        let (down, left) = (self.is_direction_visited(Direction::Down), self.is_direction_visited(Direction::Left));
+        Some(match (up || down, left || right) {
+            (false, false) => return None,
+            (true, true) => '+',
+            (true, false) if up && down => '|',
+            (true, false) if up => '^',
+            (true, false) => 'v',
+            (false, true) if left && right => '-',
+            (false, true) if left => '<',
+            (false, true) => '>',
+        })
     }
+}
+/// A tile and the directions it was left in, packed into 16 bits.
+///
+/// Bits 0 to 3 hold the [`VisitedDirections`] of patrollers that took an even number of turns and bits 4 to 7 those
+/// of patrollers that took an odd number, bit 8 marks an obstacle and the top 7 bits stamp the generation the visits
+/// were recorded in. Visits with a stale stamp read as none, so the map forgets all of them by bumping its
+/// generation instead of rewriting every tile.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+struct PackedTile(u16);
+impl PackedTile {
+    const VISITS: u16 = 0b1111_1111;
+    const ODD_VISITS_SHIFT: u16 = 4;
+    const OBSTACLE: u16 = 1 << 8;
+    const GENERATION_SHIFT: u16 = 9;
+    /// Number of distinct generation stamps.
+    pub const GENERATIONS: u16 = 1 << (16 - Self::GENERATION_SHIFT);
     #[inline]
-    pub const fn set_down(&mut self) -> &mut Self {
-        self.down = true;
-        self
+    pub const fn new(tile: Tile) -> Self {
+        Self(match tile {
+            Tile::Obstacle => Self::OBSTACLE,
+            Tile::Air => 0,
+        })
     }
     #[inline]
-    pub const fn set_left(&mut self) -> &mut Self {
-        self.left = true;
-        self
+    pub const fn tile(self) -> Tile {
+        if self.0 & Self::OBSTACLE == 0 {
+            Tile::Air
+        } else {
+            Tile::Obstacle
+        }
     }
     #[inline]
-    pub const fn is_direction_visited(&self, dir: Direction) -> bool {
-        match dir {
-            Direction::Up => self.up,
-            Direction::Right => self.right,
-            Direction::Down => self.down,
-            Direction::Left => self.left,
+    pub const fn set_tile(&mut self, tile: Tile) {
+        self.0 = (self.0 & !Self::OBSTACLE) | Self::new(tile).0;
+    }
+    // both parities' visits, none if they were stamped with another generation
+    const fn current_visits(self, generation: u16) -> u16 {
+        if self.0 >> Self::GENERATION_SHIFT == generation {
+            self.0 & Self::VISITS
+        } else {
+            0
         }
     }
+    /// Directions the tile was left in by patrollers that took an odd number of turns or by those that didn't.
     #[inline]
-    pub const fn is_all_visited(&self) -> bool {
-        self.up && self.right && self.down && self.left
+    pub const fn visits(self, generation: u16, odd_turns: bool) -> VisitedDirections {
+        let shift = if odd_turns { Self::ODD_VISITS_SHIFT } else { 0 };
+        VisitedDirections((self.current_visits(generation) >> shift) as u8 & 0b1111)
     }
+    /// Every direction the tile was left in, whatever the number of turns.
     #[inline]
-    pub const fn is_any_visited(&self) -> bool {
-        self.up || self.right || self.down || self.left
+    pub const fn trail(self, generation: u16) -> VisitedDirections {
+        let visits = self.current_visits(generation);
+        VisitedDirections((visits | visits >> Self::ODD_VISITS_SHIFT) as u8 & 0b1111)
     }
     #[inline]
-    pub const fn set_direction(&mut self, dir: Direction) {
-        match dir {
-            Direction::Up => self.set_up(),
-            Direction::Right => self.set_right(),
-            Direction::Down => self.set_down(),
-            Direction::Left => self.set_left(),
-        };
+    pub const fn visit(&mut self, dir: Direction, odd_turns: bool, generation: u16) {
+        let shift = if odd_turns { Self::ODD_VISITS_SHIFT } else { 0 };
+        let visit = 1 << (dir as u16 + shift);
+        self.0 = generation << Self::GENERATION_SHIFT | (self.0 & Self::OBSTACLE) | self.current_visits(generation) | visit;
+    }
+    #[inline]
+    pub const fn clear_visits(&mut self) {
+        self.0 &= Self::OBSTACLE;
     }
 }
 impl TryFrom<u8> for Tile {
@@ -364,72 +628,187 @@ impl TryFrom<u8> for Tile {
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         Ok(match value {
             b'#' => Self::Obstacle,
-            b'.' | b'^' => Self::Air,
+            b'.' | b'^' | b'>' | b'v' | b'<' => Self::Air,
             _ => return Err(format!("Invalid tile: {}", value as char)),
         })
     }
 }
 #[inline]
-pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> i32 {
-    let mut map = NavigatableMap::new(input);
-    loop {
-        let res = map.step();
-        match res {
-            Some(res) => match res {
-                HasFinished::OutOfBounds | HasFinished::LoopEntered => break,
-                HasFinished::Unfinished => {
-                    // eprintln!("{}", map.visited_tiles.str(|visiteddir| { format!("{:?}", visiteddir) }));
-                    continue;
-                }
-            },
-            None => {
-                panic!("Some error");
-            }
-        }
+pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel, turn_policy: TurnPolicy) -> i32 {
+    NavigatableMap::new(input).with_turn_policy(turn_policy).patrol().tiles().len() as i32
+}
+#[inline]
+pub fn print_looping_obstacles(input: &str, turn_policy: TurnPolicy, sentinel: &SolverSentinel) {
+    let looping = NavigatableMap::new(input).with_turn_policy(turn_policy).looping_obstacles();
+    sentinel_println!(Sentinel: sentinel, println!("day 6: {} obstacle positions trap the guard", looping.len()));
+    for obstacle in &looping {
+        sentinel_println!(Sentinel: sentinel, println!("\t{}", obstacle));
     }
-    let visited_tiles = map.visited_tiles.data.iter().filter(|&visited| visited.is_any_visited()).count();
-    visited_tiles as i32 + 1 // account for goddamn first tile, has a chance to be wrong if the patroller somehow passes through the starting square with a different direction.
 }
 #[inline]
-pub fn solve_part_2(input: &str, _sentinel: &mut SolverSentinel) -> i32 {
-    let mut map = NavigatableMap::new(input);
-    let mut total = 0;
-    let original_patroller = map.patroller.clone();
-    let original_visited_tiles = map.visited_tiles.clone();
-    for (index1d, tile) in map.tiles.data.clone().into_iter().enumerate() {
-        if index1d == map.patroller.pos.1 * map.w + map.patroller.pos.0 {
-            continue;
+pub fn print_patrol(input: &str, turn_policy: TurnPolicy, sentinel: &SolverSentinel) {
+    let path = NavigatableMap::new(input).with_turn_policy(turn_policy).patrol();
+    sentinel_println!(Sentinel: sentinel, println!("day 6: the guard {}", path));
+    for turn in path.turn_points() {
+        sentinel_println!(Sentinel: sentinel, println!("\tturns at {:?} heading {:?}", turn.pos, turn.dir));
+    }
+}
+#[inline]
+pub fn solve_part_2(input: &str, _sentinel: &mut SolverSentinel, turn_policy: TurnPolicy) -> i32 {
+    let original = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    let mut map = original.clone();
+    let mut total = original.unreached_traps().len() as i32;
+    for ((x, y), start) in original.first_entries() {
+        if map.loops_with_obstacle_at(x, y, &start) {
+            total += 1;
         }
-        match tile {
-            Tile::Air => {
-                let x = index1d % map.w;
-                let y = index1d / map.w;
-                // set the tile to an obstacle in new map
-                let mut previous_tile = Tile::Obstacle;
-                mem::swap(map.tiles.get_mut(x, y).unwrap(), &mut previous_tile);
-                'a: loop {
-                    let res = map.step();
-                    match res.unwrap() {
-                        HasFinished::LoopEntered => {
-                            total += 1;
-                            break 'a;
-                        }
-                        HasFinished::OutOfBounds => {
-                            break 'a;
-                        }
-                        HasFinished::Unfinished => {
-                            continue;
-                        }
-                    }
-                }
-                mem::swap(map.tiles.get_mut(x, y).unwrap(), &mut previous_tile);
-                map.patroller.clone_from(&original_patroller);
-                map.visited_tiles.data.clone_from_slice(&original_visited_tiles.data);
+    }
+    total
+}
+/// Same answer as [`solve_part_2`], with the candidates spread over a rayon pool of `threads` workers
+/// (rayon's default when `None`). Every worker walks its own copy of the map.
+#[inline]
+pub fn solve_part_2_parallel(input: &str, _sentinel: &mut SolverSentinel, turn_policy: TurnPolicy, threads: Option<usize>) -> i32 {
+    let original = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    let candidates = original.first_entries();
+    let pool = ThreadPoolBuilder::new().num_threads(threads.unwrap_or(0)).build().unwrap();
+    let loops = pool.install(|| {
+        candidates
+            .par_iter()
+            .map_init(
+                || original.clone(),
+                |scratch, &((x, y), ref start)| scratch.loops_with_obstacle_at(x, y, start),
+            )
+            .filter(|&loops| loops)
+            .count()
+    });
+    (loops + original.unreached_traps().len()) as i32
+}
+/// Part 2 on the [`jump::JumpTable`]: every candidate walk only visits the guard's turns.
+#[inline]
+pub fn solve_part_2_jump(input: &str, _sentinel: &mut SolverSentinel, turn_policy: TurnPolicy, threads: Option<usize>) -> i32 {
+    let map = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    let table = jump::JumpTable::new(&map);
+    let candidates = map.first_entries();
+    let pool = ThreadPoolBuilder::new().num_threads(threads.unwrap_or(0)).build().unwrap();
+    let loops = pool.install(|| {
+        candidates
+            .par_iter()
+            .filter(|&&(obstacle, ref start)| table.walk(start, Some(obstacle)) == HasFinished::LoopEntered)
+            .count()
+    });
+    (loops + map.unreached_traps().len()) as i32
+}
+/// Part 1 on the [`sparse::SparseMap`], for maps too large to hold tile by tile.
+#[inline]
+pub fn solve_part_1_sparse(input: &str, _sentinel: &mut SolverSentinel, turn_policy: TurnPolicy) -> u64 {
+    let map = input.parse::<sparse::SparseMap>().unwrap_or_else(
+        #[cold]
+        |err| panic!("Invalid day 6 map: {}", err),
+    );
+    map.with_turn_policy(turn_policy).covered_tiles()
+}
+/// Part 2 on the [`sparse::SparseMap`]. Only the candidate list grows with the length of the walk.
+#[inline]
+pub fn solve_part_2_sparse(input: &str, _sentinel: &mut SolverSentinel, turn_policy: TurnPolicy, threads: Option<usize>) -> usize {
+    let map = input
+        .parse::<sparse::SparseMap>()
+        .unwrap_or_else(
+            #[cold]
+            |err| panic!("Invalid day 6 map: {}", err),
+        )
+        .with_turn_policy(turn_policy);
+    let candidates = map.first_entries();
+    let pool = ThreadPoolBuilder::new().num_threads(threads.unwrap_or(0)).build().unwrap();
+    let loops = pool.install(|| {
+        candidates
+            .par_iter()
+            .filter(|&&(obstacle, ref start)| map.walk_with(start, Some(obstacle), |_, _| {}) == HasFinished::LoopEntered)
+            .count()
+    });
+    loops + map.unreached_traps()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+    use ahash::AHashSet;
+    // the tiles covered by a plain tile by tile walk, and whether it loops
+    fn brute_force_walk(rows: &[&[u8]], start: &Patroller, turn_policy: TurnPolicy, extra: (usize, usize)) -> (AHashSet<(usize, usize)>, bool) {
+        let mut patroller = start.clone();
+        let (mut tiles, mut states) = (AHashSet::from([start.pos]), AHashSet::new());
+        while states.insert(patroller.clone()) {
+            let (x, y) = patroller.pos;
+            let Some((nx, ny)) = patroller.dir.offset_bounded(x, y, rows[0].len(), rows.len()) else {
+                return (tiles, false);
+            };
+            if rows[ny][nx] == b'#' || (nx, ny) == extra {
+                turn_policy.turn(&mut patroller);
+            } else {
+                patroller.pos = (nx, ny);
+                tiles.insert((nx, ny));
             }
-            Tile::Obstacle => {
-                continue;
+        }
+        (tiles, true)
+    }
+    #[test]
+    fn visits_cover_the_walk_under_every_policy() {
+        let input = "....#.....\n.........#\n..........\n..#.......\n.......#..\n..........\n.#..^.....\n........#.\n#.........\n......#...";
+        for turn_policy in [TurnPolicy::Right, TurnPolicy::Left, TurnPolicy::Alternate, TurnPolicy::UTurn] {
+            let mut map = NavigatableMap::new(input).with_turn_policy(turn_policy);
+            let path = map.patrol();
+            while map.step() == HasFinished::Unfinished {}
+            // every tile is left again except the one the patroller stops on
+            let left = path.tiles().filter(|&pos| pos != map.patroller.pos).sorted().collect::<Vec<_>>();
+            let marked = (0..map.h)
+                .flat_map(|y| (0..map.w).map(move |x| (x, y)))
+                .filter(|&(x, y)| map.visits_at(x, y).glyph().is_some() && (x, y) != map.patroller.pos)
+                .sorted()
+                .collect::<Vec<_>>();
+            assert_eq!(marked, left, "tiles marked under {:?}", turn_policy);
+        }
+    }
+    #[test]
+    #[should_panic(expected = "Patroller starts outside the map")]
+    fn walks_reject_starts_outside_the_map() {
+        let mut map = NavigatableMap::new("..\n^.");
+        let start = Patroller {
+            pos: (2, 0),
+            dir: Direction::Up,
+            odd_turns: false,
+        };
+        map.walk_with_obstacle_at(0, 0, &start);
+    }
+    #[test]
+    fn every_backend_matches_brute_force() {
+        let mut rng = Xorshift::new();
+        let mut sentinel = SolverSentinel::new();
+        for _ in 0..300 {
+            let (w, h) = (rng.below(8) + 2, rng.below(8) + 2);
+            let mut tiles = (0..w * h).map(|_| if rng.below(4) == 0 { b'#' } else { b'.' }).collect::<Vec<_>>();
+            let guard = rng.below(w * h);
+            tiles[guard] = b"^>v<"[rng.below(4)];
+            let rows = tiles.chunks(w).collect::<Vec<_>>();
+            let input = rows.iter().map(|row| String::from_utf8_lossy(row)).join("\n");
+            let start = NavigatableMap::new(&input).patroller;
+            for turn_policy in [TurnPolicy::Right, TurnPolicy::Left, TurnPolicy::Alternate, TurnPolicy::UTurn] {
+                let part_1 = brute_force_walk(&rows, &start, turn_policy, (w, h)).0.len();
+                let part_2 = (0..w * h)
+                    .filter(|&i| tiles[i] == b'.' && brute_force_walk(&rows, &start, turn_policy, (i % w, i / w)).1)
+                    .count();
+                let parts_1 = [
+                    solve_part_1(&input, &mut sentinel, turn_policy) as u64,
+                    solve_part_1_sparse(&input, &mut sentinel, turn_policy),
+                ];
+                assert_eq!(parts_1, [part_1 as u64; 2], "part 1 under {:?} of\n{}", turn_policy, input);
+                let parts_2 = [
+                    solve_part_2(&input, &mut sentinel, turn_policy) as usize,
+                    solve_part_2_parallel(&input, &mut sentinel, turn_policy, Some(1)) as usize,
+                    solve_part_2_jump(&input, &mut sentinel, turn_policy, Some(1)) as usize,
+                    solve_part_2_sparse(&input, &mut sentinel, turn_policy, Some(1)),
+                ];
+                assert_eq!(parts_2, [part_2; 4], "part 2 under {:?} of\n{}", turn_policy, input);
             }
         }
     }
-    total
 }
@@ -0,0 +1,121 @@
+use super::{NavigatableMap, PackedTile, Patroller, Tile, TurnPolicy};
+use crate::grid::Grid;
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use core::mem;
+use core::time::Duration;
+use voxell_timer::time;
+// visits of one tile in the layout before the packing: four bools per parity of the turns taken
+type UnpackedVisits = [[bool; 4]; 2];
+/// The map as it was laid out before [`PackedTile`]: a grid of tiles, a separate grid of visits, and a copy of the
+/// untouched visits to restore after every walk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UnpackedMap {
+    tiles: Grid<Tile>,
+    visits: Vec<UnpackedVisits>,
+    untouched: Vec<UnpackedVisits>,
+    turn_policy: TurnPolicy,
+}
+impl UnpackedMap {
+    fn new(map: &NavigatableMap) -> Self {
+        let untouched = vec![[[false; 4]; 2]; map.w * map.h];
+        Self {
+            tiles: map.tiles.map(|tile| tile.tile()),
+            visits: untouched.clone(),
+            untouched,
+            turn_policy: map.turn_policy,
+        }
+    }
+    // same walk as `NavigatableMap::walk_with_obstacle_at`, restoring the visits afterwards
+    fn loops_with_obstacle_at(&mut self, obstacle: (usize, usize), start: &Patroller) -> bool {
+        let (w, h) = (self.tiles.width(), self.tiles.height());
+        let mut patroller = start.clone();
+        let loops = loop {
+            let (x, y) = patroller.pos;
+            let visited = &mut self.visits[y * w + x][usize::from(patroller.odd_turns)][patroller.dir as usize];
+            if *visited {
+                break true;
+            }
+            *visited = true;
+            let Some(ahead) = patroller.dir.offset_bounded(x, y, w, h) else {
+                break false;
+            };
+            if ahead == obstacle || self.tiles[ahead] == Tile::Obstacle {
+                self.turn_policy.turn(&mut patroller);
+            } else {
+                patroller.pos = ahead;
+            }
+        };
+        self.visits.clone_from_slice(&self.untouched);
+        loops
+    }
+}
+// walks every part 2 candidate with `loops` once
+fn time_candidates<F>(candidates: &[((usize, usize), Patroller)], mut loops: F) -> (usize, Duration)
+where
+    F: FnMut((usize, usize), &Patroller) -> bool,
+{
+    time!(candidates.iter().filter(|&&(obstacle, ref start)| loops(obstacle, start)).count())
+}
+/// Times part 2's walks with generation stamped resets, with rewriting every tile after each candidate, and on the
+/// unpacked layout the packed tiles replaced.
+#[inline]
+pub fn print_benchmarks(input: &str, turn_policy: TurnPolicy, runs: usize, sentinel: &SolverSentinel) {
+    let original = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    let candidates = original.first_entries();
+    let mut map = original.clone();
+    let mut unpacked_map = UnpackedMap::new(&original);
+    let (mut stamped, mut cleared, mut unpacked) = (Duration::MAX, Duration::MAX, Duration::MAX);
+    let (mut stamped_loops, mut cleared_loops, mut unpacked_loops) = (0, 0, 0);
+    // the layouts take turns within every run, so the other days' solvers slow all of them down alike
+    for _ in 0..runs {
+        let elapsed;
+        (stamped_loops, elapsed) = time_candidates(&candidates, |(x, y), start| {
+            let loops = map.walk_with_obstacle_at(x, y, start);
+            map.reset_visits();
+            loops
+        });
+        stamped = stamped.min(elapsed);
+        let elapsed;
+        (cleared_loops, elapsed) = time_candidates(&candidates, |(x, y), start| {
+            let loops = map.walk_with_obstacle_at(x, y, start);
+            map.clear_visits();
+            loops
+        });
+        cleared = cleared.min(elapsed);
+        let elapsed;
+        (unpacked_loops, elapsed) = time_candidates(&candidates, |obstacle, start| unpacked_map.loops_with_obstacle_at(obstacle, start));
+        unpacked = unpacked.min(elapsed);
+    }
+    assert_eq!(stamped_loops, cleared_loops, "Both resets must find the same loops");
+    assert_eq!(stamped_loops, unpacked_loops, "Both layouts must find the same loops");
+    let (packed_size, unpacked_size) = (mem::size_of::<PackedTile>(), mem::size_of::<Tile>() + mem::size_of::<UnpackedVisits>());
+    sentinel_println!(
+        Sentinel: sentinel,
+        println!(
+            "day 6: {} candidates over {} tiles at {} bytes each, {} unpacked, {} loops",
+            candidates.len(),
+            original.w * original.h,
+            packed_size,
+            unpacked_size,
+            stamped_loops
+        )
+    );
+    sentinel_println!(
+        Sentinel: sentinel,
+        println!(
+            "day 6: fastest run with generation resets {:?}, with full clears {:?}, unpacked with slice copies {:?}",
+            stamped, cleared, unpacked
+        )
+    );
+    // anything within a few percent is noise between runs
+    let speedup = unpacked.as_secs_f64() / stamped.as_secs_f64();
+    if speedup > 1.05 {
+        sentinel_println!(Sentinel: sentinel, println!("day 6: the packed layout is {:.2}x as fast as the unpacked one", speedup));
+    } else {
+        sentinel_println!(
+            Sentinel: sentinel,
+            println!("day 6: the packed layout is no faster than the unpacked one ({:.2}x), it only takes less memory", speedup)
+        );
+    }
+}
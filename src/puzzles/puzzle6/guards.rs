@@ -0,0 +1,250 @@
+extern crate alloc;
+use super::{NavigatableMap, PatrolEnd, PatrolPath, TurnPolicy};
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+use itertools::Itertools;
+use std::fs;
+use std::path::Path;
+/// Guards standing on the same tile after the same number of steps.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Collision {
+    pub tick: usize,
+    pub pos: (usize, usize),
+    pub guards: Vec<usize>,
+    /// For two guards going round their loops, how many ticks pass until they meet there again.
+    pub every: Option<usize>,
+}
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "guards {} meet at {:?} after {} steps",
+            self.guards.iter().join(", "),
+            self.pos,
+            self.tick
+        )?;
+        if let Some(every) = self.every {
+            write!(f, " and again every {} steps", every)?;
+        }
+        Ok(())
+    }
+}
+/// Every guard of a map walking in lockstep, one step or turn per tick each.
+///
+/// Guards don't block each other, each follows the path it would walk alone. A guard that left the map is gone,
+/// one that entered its loop keeps going round it. Collisions are listed tick by tick until every guard has left or
+/// entered its loop, later ones once per pair of looping guards with the tick they first meet and how often they
+/// meet again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Lockstep {
+    paths: Vec<PatrolPath>,
+    collisions: Vec<Collision>,
+}
+impl Lockstep {
+    fn new(map: &NavigatableMap) -> Self {
+        let paths = map.guards.iter().map(|guard| map.patrol_from(guard)).collect::<Vec<_>>();
+        // from this tick on every guard has either left or is going round its loop
+        let settled = paths
+            .iter()
+            .map(|path| match *path.end() {
+                PatrolEnd::Exit(_) => path.steps().len(),
+                PatrolEnd::Loop { entry } => entry,
+            })
+            .max()
+            .unwrap_or(0);
+        let mut meetings: BTreeMap<(usize, (usize, usize)), BTreeSet<usize>> = BTreeMap::new();
+        for tick in 0..settled {
+            let mut by_tile: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+            for (index, path) in paths.iter().enumerate() {
+                if let Some(guard) = path.position_at(tick) {
+                    by_tile.entry(guard.pos).or_default().push(index);
+                }
+            }
+            for (pos, guards) in by_tile.into_iter().filter(|tile| tile.1.len() > 1) {
+                meetings.entry((tick, pos)).or_default().extend(guards);
+            }
+        }
+        // after that two looping guards meet on a tile at the ticks that fit both their loops there, which repeat
+        // with the lcm of just those two loops
+        let loops = paths
+            .iter()
+            .enumerate()
+            .filter_map(|(index, path)| match *path.end() {
+                PatrolEnd::Exit(_) => None,
+                PatrolEnd::Loop { entry } => Some((index, loop_residues(path, entry), path.steps().len() - entry)),
+            })
+            .collect::<Vec<_>>();
+        let mut repeating = Vec::new();
+        for (&(a, ref at_a, len_a), &(b, ref at_b, len_b)) in loops.iter().tuple_combinations() {
+            for (&pos, residues_a) in at_a {
+                let Some(residues_b) = at_b.get(&pos) else {
+                    continue;
+                };
+                for (&residue_a, &residue_b) in residues_a.iter().cartesian_product(residues_b) {
+                    let Some((first, every)) = solve_congruences((residue_a, len_a), (residue_b, len_b)) else {
+                        continue;
+                    };
+                    repeating.push(Collision {
+                        tick: settled + (first + every - settled % every) % every,
+                        pos,
+                        guards: vec![a, b],
+                        every: Some(every),
+                    });
+                }
+            }
+        }
+        let collisions = meetings
+            .into_iter()
+            .map(|((tick, pos), guards)| Collision {
+                tick,
+                pos,
+                guards: guards.into_iter().collect(),
+                every: None,
+            })
+            .chain(repeating)
+            .sorted_by_key(|collision| (collision.tick, collision.pos, collision.guards.clone()))
+            .collect();
+        Self { paths, collisions }
+    }
+    /// Tiles at least one guard walks over.
+    fn covered(&self) -> usize {
+        self.paths.iter().flat_map(PatrolPath::tiles).unique().count()
+    }
+}
+/// For every tile of a loop entered at step `entry`, the ticks modulo the loop length at which the guard stands on it.
+fn loop_residues(path: &PatrolPath, entry: usize) -> BTreeMap<(usize, usize), Vec<usize>> {
+    let len = path.steps().len() - entry;
+    let mut residues: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+    for (tick, guard) in path.steps().iter().enumerate().skip(entry) {
+        residues.entry(guard.pos).or_default().push(tick % len);
+    }
+    residues
+}
+const fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+const fn lcm(a: usize, b: usize) -> usize {
+    a / gcd(a, b) * b
+}
+/// The ticks `t` with `t % m == r` for both `(r, m)`, as the smallest one and how often it repeats.
+const fn solve_congruences((r1, m1): (usize, usize), (r2, m2): (usize, usize)) -> Option<(usize, usize)> {
+    let g = gcd(m1, m2);
+    if r1.abs_diff(r2) % g != 0 {
+        return None;
+    }
+    let every = lcm(m1, m2);
+    // t = r1 + k * m1 where k * (m1 / g) == (r2 - r1) / g modulo m2 / g
+    let (m, step) = ((m2 / g) as i128, (m1 / g) as i128);
+    let k = ((r2 as i128 - r1 as i128) / g as i128).rem_euclid(m) * inverse_mod(step, m) % m;
+    Some(((r1 as i128 + k * m1 as i128).rem_euclid(every as i128) as usize, every))
+}
+/// Inverse of `value` modulo `modulus`, which must be coprime to it.
+const fn inverse_mod(value: i128, modulus: i128) -> i128 {
+    // extended euclid, keeping only the coefficients of `value`
+    let (mut rem, mut next_rem, mut coef, mut next_coef) = (value.rem_euclid(modulus), modulus, 1_i128, 0_i128);
+    while next_rem != 0 {
+        let quotient = rem / next_rem;
+        (rem, next_rem) = (next_rem, rem - quotient * next_rem);
+        (coef, next_coef) = (next_coef, coef - quotient * next_coef);
+    }
+    coef.rem_euclid(modulus)
+}
+/// Walks every guard of the map in `path` in lockstep and reports their routes and meetings.
+#[inline]
+pub fn print_guards(path: &Path, turn_policy: TurnPolicy, sentinel: &SolverSentinel) -> Result<(), String> {
+    let input = fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    let map = NavigatableMap::with_guards(&input)
+        .map_err(|err| format!("Invalid day 6 map {}: {}", path.display(), err))?
+        .with_turn_policy(turn_policy);
+    let lockstep = Lockstep::new(&map);
+    for (index, (guard, path)) in map.guards.iter().zip(&lockstep.paths).enumerate() {
+        sentinel_println!(
+            Sentinel: sentinel,
+            println!("day 6: guard {} starting at {:?} facing {:?} {}", index, guard.pos, guard.dir, path)
+        );
+    }
+    sentinel_println!(Sentinel: sentinel, println!("day 6: the guards cover {} tiles together", lockstep.covered()));
+    for collision in &lockstep.collisions {
+        sentinel_println!(Sentinel: sentinel, println!("\t{}", collision));
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+    #[test]
+    fn guards_meet_after_their_recorded_paths() {
+        let map = "..#......./......#.../........../....#...../.#....<.#./...#..#.../.#......##/....##..../..#....v../....####..";
+        let map = NavigatableMap::with_guards(&map.replace('/', "\n")).unwrap();
+        let lockstep = Lockstep::new(&map);
+        assert_eq!(
+            lockstep.paths.iter().map(|path| path.steps().len()).collect::<Vec<_>>(),
+            [26, 17],
+            "recorded paths"
+        );
+        assert_eq!(lockstep.collisions.first().map(|collision| collision.tick), Some(42), "first meeting");
+    }
+    #[test]
+    fn collisions_match_ticking_through_a_full_period() {
+        let mut rng = Xorshift::new();
+        for _ in 0..1000 {
+            let (w, h) = (rng.below(5) + 3, rng.below(5) + 3);
+            // a mostly closed frame of obstacles keeps most guards on the map and walking in loops
+            let mut tiles = (0..w * h)
+                .map(|i| {
+                    let edge = i % w == 0 || i % w == w - 1 || i / w == 0 || i / w == h - 1;
+                    let open = if edge { rng.below(8) == 0 } else { rng.below(5) != 0 };
+                    if open {
+                        b'.'
+                    } else {
+                        b'#'
+                    }
+                })
+                .collect::<Vec<_>>();
+            for _ in 0..=rng.below(6) {
+                tiles[rng.below(w * h)] = b"^>v<"[rng.below(4)];
+            }
+            let input = tiles.chunks(w).map(|row| String::from_utf8_lossy(row)).join("\n");
+            let Ok(map) = NavigatableMap::with_guards(&input) else {
+                continue;
+            };
+            let lockstep = Lockstep::new(&map);
+            // every guard settled, then one full period of all the loops together
+            let ends = lockstep.paths.iter().map(|path| match *path.end() {
+                PatrolEnd::Exit(_) => (path.steps().len(), None),
+                PatrolEnd::Loop { entry } => (entry, Some(path.steps().len() - entry)),
+            });
+            let (settled, period) = (ends.clone().map(|end| end.0).max(), ends.filter_map(|end| end.1));
+            let horizon = settled.unwrap_or(0) + period.fold(1, lcm);
+            let expected = (0..horizon)
+                .flat_map(|tick| {
+                    let guards = lockstep
+                        .paths
+                        .iter()
+                        .enumerate()
+                        .filter_map(move |(index, path)| Some((path.position_at(tick)?.pos, index)));
+                    guards
+                        .into_group_map()
+                        .into_iter()
+                        .filter(|tile| tile.1.len() > 1)
+                        .map(move |(pos, guards)| ((tick, pos), guards.into_iter().collect::<BTreeSet<_>>()))
+                })
+                .collect::<BTreeMap<_, _>>();
+            // repeating meetings played out up to the same tick
+            let mut found: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+            for collision in &lockstep.collisions {
+                for tick in (collision.tick..horizon).step_by(collision.every.unwrap_or(horizon)) {
+                    found.entry((tick, collision.pos)).or_default().extend(&collision.guards);
+                }
+            }
+            assert_eq!(found, expected, "collisions of\n{}", input);
+        }
+    }
+}
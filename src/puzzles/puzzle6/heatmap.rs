@@ -0,0 +1,131 @@
+use super::{HasFinished, NavigatableMap, Tile, TurnPolicy};
+use crate::grid::Grid;
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use itertools::Itertools;
+use std::fs;
+use std::io;
+use std::path::Path;
+/// Per tile, how many of part 2's simulations walked over it, and how many of those ended in a loop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Heatmap {
+    passed: Grid<u32>,
+    looped: Grid<u32>,
+    /// Simulations that ended in a loop, which is part 2's answer.
+    loops: usize,
+}
+impl Heatmap {
+    // every candidate is walked from the guard's start, counting each tile once per simulation
+    fn new(map: &NavigatableMap) -> Self {
+        let (w, h) = (map.w, map.h);
+        let mut passed = vec![0; w * h];
+        let mut looped = vec![0; w * h];
+        let mut last_counted = vec![usize::MAX; w * h];
+        let mut walked = Vec::new();
+        let mut walker = map.clone();
+        let mut loops = 0;
+        for (simulation, ((x, y), _)) in map.first_entries().into_iter().enumerate() {
+            walker.patroller.clone_from(&map.patroller);
+            walker.set_tile(x, y, Tile::Obstacle);
+            walked.clear();
+            let finished = loop {
+                let index = walker.patroller.pos.1 * w + walker.patroller.pos.0;
+                if last_counted[index] != simulation {
+                    last_counted[index] = simulation;
+                    walked.push(index);
+                }
+                match walker.step() {
+                    HasFinished::Unfinished => {}
+                    finished @ (HasFinished::LoopEntered | HasFinished::OutOfBounds) => break finished,
+                }
+            };
+            walker.set_tile(x, y, Tile::Air);
+            walker.reset_visits();
+            for &index in &walked {
+                passed[index] += 1;
+                if finished == HasFinished::LoopEntered {
+                    looped[index] += 1;
+                }
+            }
+            loops += usize::from(finished == HasFinished::LoopEntered);
+        }
+        // an obstacle off the path leaves the looping walk as it is, so each of them walks the unobstructed patrol
+        let traps = map.unreached_traps().len();
+        for (x, y) in map.patrol().tiles().unique() {
+            passed[y * w + x] += traps as u32;
+            looped[y * w + x] += traps as u32;
+        }
+        Self {
+            passed: Grid::new(w, h, passed),
+            looped: Grid::new(w, h, looped),
+            loops: loops + traps,
+        }
+    }
+}
+/// Shades every tile by its count relative to the largest one, from ` ` for never up to `@`.
+fn shade(heat: &Grid<u32>) -> String {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+    let max = heat.iter().copied().max().unwrap_or(0).max(1);
+    heat.render(|&count| {
+        // anything walked at all gets at least the first visible shade
+        let level = (count as usize * (RAMP.len() - 1)).div_ceil(max as usize);
+        char::from(RAMP[level]).to_string()
+    })
+}
+/// Binary PPM with a black, red, yellow, white ramp.
+fn to_ppm(heat: &Grid<u32>) -> Vec<u8> {
+    let max = heat.iter().copied().max().unwrap_or(0).max(1);
+    let mut ppm = format!("P6\n{} {}\n255\n", heat.width(), heat.height()).into_bytes();
+    for &count in heat.iter() {
+        let t = count as f64 / max as f64 * 3.0;
+        ppm.extend([t, t - 1.0, t - 2.0].map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+    }
+    ppm
+}
+#[inline]
+pub fn print_heatmaps(input: &str, turn_policy: TurnPolicy, sentinel: &SolverSentinel) {
+    let heatmap = Heatmap::new(&NavigatableMap::new(input).with_turn_policy(turn_policy));
+    sentinel_println!(Sentinel: sentinel, println!("day 6: tiles walked by every part 2 simulation\n{}", shade(&heatmap.passed)));
+    sentinel_println!(
+        Sentinel: sentinel,
+        println!(
+            "day 6: tiles walked by the {} looping simulations\n{}",
+            heatmap.loops,
+            shade(&heatmap.looped)
+        )
+    );
+}
+/// Writes `passed.ppm` and `looped.ppm` to `dir`.
+#[inline]
+pub fn write_heatmaps(input: &str, turn_policy: TurnPolicy, dir: &Path) -> io::Result<()> {
+    let heatmap = Heatmap::new(&NavigatableMap::new(input).with_turn_policy(turn_policy));
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("passed.ppm"), to_ppm(&heatmap.passed))?;
+    fs::write(dir.join("looped.ppm"), to_ppm(&heatmap.looped))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::puzzle6::solve_part_2;
+    use crate::puzzles::test_rng::Xorshift;
+    use crate::INPUT6;
+    #[test]
+    fn looping_simulations_match_part_2() {
+        let mut rng = Xorshift::new();
+        let mut sentinel = SolverSentinel::new();
+        for _ in 0..200 {
+            let (w, h) = (rng.below(8) + 2, rng.below(8) + 2);
+            let mut tiles = (0..w * h).map(|_| if rng.below(4) == 0 { b'#' } else { b'.' }).collect::<Vec<_>>();
+            tiles[rng.below(w * h)] = b"^>v<"[rng.below(4)];
+            let input = tiles.chunks(w).map(|row| String::from_utf8_lossy(row)).join("\n");
+            for turn_policy in [TurnPolicy::Right, TurnPolicy::Left, TurnPolicy::Alternate, TurnPolicy::UTurn] {
+                let heatmap = Heatmap::new(&NavigatableMap::new(&input).with_turn_policy(turn_policy));
+                let part_2 = solve_part_2(&input, &mut sentinel, turn_policy) as usize;
+                assert_eq!(heatmap.loops, part_2, "looping simulations under {:?} of\n{}", turn_policy, input);
+            }
+        }
+        // the u-turn walk on the real input loops by itself, so nearly every obstacle is an unreached trap
+        let heatmap = Heatmap::new(&NavigatableMap::new(&INPUT6).with_turn_policy(TurnPolicy::UTurn));
+        assert_eq!(heatmap.loops, 16086, "looping simulations of the u-turn walk on the real input");
+    }
+}
@@ -0,0 +1,100 @@
+use super::{HasFinished, NavigatableMap, PackedTile, Patroller, Tile, TurnPolicy};
+use crate::grid::Direction;
+use ahash::AHashSet;
+/// For every tile and direction, where the patroller ends up when it walks straight until the next obstacle.
+///
+/// A walk then costs one lookup per turn instead of one step per tile, and since only turns are visited,
+/// loops are detected on repeated turn states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpTable {
+    w: usize,
+    h: usize,
+    // stops[dir as usize][y * w + x]: last free tile before the next obstacle, `None` when the patroller walks off
+    stops: [Vec<Option<(usize, usize)>>; 4],
+    turn_policy: TurnPolicy,
+}
+impl JumpTable {
+    #[inline]
+    pub fn new(map: &NavigatableMap) -> Self {
+        let (w, h) = (map.w, map.h);
+        let is_obstacle = |tile: &PackedTile| tile.tile() == Tile::Obstacle;
+        let mut stops: [Vec<Option<(usize, usize)>>; 4] = Default::default();
+        for dir_stops in &mut stops {
+            *dir_stops = vec![None; w * h];
+        }
+        for x in 0..w {
+            let mut stop = None;
+            for (y, tile) in map.tiles.column(x).enumerate() {
+                if is_obstacle(tile) {
+                    stop = Some((x, y + 1));
+                } else {
+                    stops[Direction::Up as usize][y * w + x] = stop;
+                }
+            }
+            let mut stop = None;
+            for (y, tile) in map.tiles.column(x).enumerate().rev() {
+                if is_obstacle(tile) {
+                    stop = y.checked_sub(1).map(|y| (x, y));
+                } else {
+                    stops[Direction::Down as usize][y * w + x] = stop;
+                }
+            }
+        }
+        for (y, row) in map.tiles.rows().enumerate() {
+            let mut stop = None;
+            for (x, tile) in row.iter().enumerate() {
+                if is_obstacle(tile) {
+                    stop = Some((x + 1, y));
+                } else {
+                    stops[Direction::Left as usize][y * w + x] = stop;
+                }
+            }
+            let mut stop = None;
+            for (x, tile) in row.iter().enumerate().rev() {
+                if is_obstacle(tile) {
+                    stop = x.checked_sub(1).map(|x| (x, y));
+                } else {
+                    stops[Direction::Right as usize][y * w + x] = stop;
+                }
+            }
+        }
+        Self {
+            w,
+            h,
+            stops,
+            turn_policy: map.turn_policy,
+        }
+    }
+    /// Where a patroller at `pos` facing `dir` stops, with `extra` treated as one more obstacle.
+    #[inline]
+    pub fn jump(&self, (x, y): (usize, usize), dir: Direction, extra: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        let stop = self.stops[dir as usize][y * self.w + x];
+        let Some(extra_distance) = extra.and_then(|extra| dir.distance_to((x, y), extra)) else {
+            return stop;
+        };
+        // the table's own obstacle sits one tile past its stop
+        let obstacle_distance = stop.map_or(usize::MAX, |stop| dir.distance_to((x, y), stop).unwrap_or(0) + 1);
+        if extra_distance < obstacle_distance {
+            dir.offset_times(x, y, extra_distance - 1)
+        } else {
+            stop
+        }
+    }
+    /// Walks from `start` until the patroller leaves the map or turns at a tile it already turned at facing the same way.
+    #[inline]
+    pub fn walk(&self, start: &Patroller, extra: Option<(usize, usize)>) -> HasFinished {
+        debug_assert!(start.pos.0 < self.w && start.pos.1 < self.h, "Patroller out of bounds");
+        let mut patroller = start.clone();
+        let mut turns = AHashSet::new();
+        loop {
+            let Some(pos) = self.jump(patroller.pos, patroller.dir, extra) else {
+                return HasFinished::OutOfBounds;
+            };
+            patroller.pos = pos;
+            if !turns.insert(patroller.clone()) {
+                return HasFinished::LoopEntered;
+            }
+            self.turn_policy.turn(&mut patroller);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use super::{HasFinished, NavigatableMap, Tile, TurnPolicy};
+use crate::grid::Direction;
+use crate::sentinel_println;
+use crate::solver::SolverSentinel;
+use core::iter;
+use core::str::FromStr;
+use core::time::Duration;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+/// Whether rendered frames carry ANSI colour codes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Style {
+    #[default]
+    Plain,
+    Ansi,
+}
+impl FromStr for Style {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" | "ascii" => Ok(Self::Plain),
+            "ansi" | "color" => Ok(Self::Ansi),
+            _ => Err(format!("Unknown render style, expected plain or ansi: {}", s)),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Cell {
+    Obstacle,
+    Air,
+    Visited(char),
+    Guard(Direction),
+    AddedObstacle,
+    Loop(char),
+}
+/// Marks drawn on top of the map: obstacles placed for part 2 and the tiles of a loop.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Overlay {
+    obstacles: Vec<(usize, usize)>,
+    loop_tiles: Vec<(usize, usize)>,
+}
+impl Cell {
+    fn glyph(self, style: Style) -> String {
+        let (glyph, color) = match self {
+            Self::Obstacle => ('#', "\x1b[1m"),
+            Self::Air => ('.', "\x1b[2m"),
+            Self::Visited(glyph) => (glyph, "\x1b[36m"),
+            Self::Guard(dir) => (dir.arrow(), "\x1b[1;31m"),
+            Self::AddedObstacle => ('O', "\x1b[1;33m"),
+            Self::Loop(glyph) => (glyph, "\x1b[35m"),
+        };
+        match style {
+            Style::Plain => glyph.to_string(),
+            Style::Ansi => format!("{}{}\x1b[0m", color, glyph),
+        }
+    }
+}
+/// Draws obstacles, the tiles the patroller left so far and the patroller itself.
+///
+/// A tile left in one direction shows that arrow, one crossed both ways along an axis shows `|` or `-`, and a
+/// tile left along both axes shows `+`. Loop tiles keep their glyph and added obstacles show as `O`.
+fn render(map: &NavigatableMap, style: Style, overlay: &Overlay) -> String {
+    let mut cells = map.tiles.map(|&tile| match tile.tile() {
+        Tile::Obstacle => Cell::Obstacle,
+        // without colours the loop only stands out if the way into it is left blank
+        Tile::Air if style == Style::Plain && !overlay.loop_tiles.is_empty() => Cell::Air,
+        Tile::Air => tile.trail(map.generation).glyph().map_or(Cell::Air, Cell::Visited),
+    });
+    for &(x, y) in &overlay.loop_tiles {
+        if let Some(glyph) = map.visits_at(x, y).glyph() {
+            cells[(x, y)] = Cell::Loop(glyph);
+        }
+    }
+    cells[map.patroller.pos] = Cell::Guard(map.patroller.dir);
+    // the tile the guard leaves the map from can be a candidate too
+    for &pos in &overlay.obstacles {
+        cells[pos] = Cell::AddedObstacle;
+    }
+    cells.render(|&cell| cell.glyph(style))
+}
+/// The walk rendered every `every` steps, from the untouched map up to the step that leaves it or closes a loop.
+#[inline]
+pub fn frames(input: &str, turn_policy: TurnPolicy, every: usize, style: Style) -> impl Iterator<Item = String> {
+    assert!(every > 0, "Frames must be at least one step apart");
+    let mut map = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    let mut started = false;
+    let mut finished = false;
+    iter::from_fn(move || {
+        if finished {
+            return None;
+        }
+        if started {
+            for _ in 0..every {
+                if map.step() != HasFinished::Unfinished {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        started = true;
+        Some(render(&map, style, &Overlay::default()))
+    })
+}
+/// Prints the map once the patroller is done walking.
+#[inline]
+pub fn print_render(input: &str, turn_policy: TurnPolicy, style: Style, sentinel: &SolverSentinel) {
+    let mut map = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    while map.step() == HasFinished::Unfinished {}
+    sentinel_println!(Sentinel: sentinel, println!("{}", render(&map, style, &Overlay::default())));
+}
+/// Plays the walk in the terminal, redrawing in place every `every` steps.
+#[inline]
+pub fn animate(input: &str, turn_policy: TurnPolicy, delay: Duration, every: usize, sentinel: &SolverSentinel) {
+    for frame in frames(input, turn_policy, every, Style::Ansi) {
+        // clear the screen and move the cursor home
+        sentinel_println!(Sentinel: sentinel, println!("\x1b[2J\x1b[H{}", frame));
+        thread::sleep(delay);
+    }
+}
+/// Writes the plain frames to `dir` as `frame_00000.txt`, `frame_00001.txt`, ...
+#[inline]
+pub fn dump_frames(input: &str, turn_policy: TurnPolicy, dir: &Path, every: usize) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (index, frame) in frames(input, turn_policy, every, Style::Plain).enumerate() {
+        fs::write(dir.join(format!("frame_{:05}.txt", index)), frame)?;
+    }
+    Ok(())
+}
+/// Prints the unobstructed walk with every obstacle position that traps the patroller marked.
+#[inline]
+pub fn print_looping_obstacles(input: &str, turn_policy: TurnPolicy, style: Style, sentinel: &SolverSentinel) {
+    let mut map = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    let overlay = Overlay {
+        obstacles: map.looping_obstacles().into_iter().map(|looping| looping.obstacle).collect(),
+        loop_tiles: Vec::new(),
+    };
+    while map.step() == HasFinished::Unfinished {}
+    sentinel_println!(Sentinel: sentinel, println!("{}", render(&map, style, &overlay)));
+}
+/// Prints the walk with an extra obstacle at `(x, y)`, highlighting the loop it gets trapped in if any.
+#[inline]
+pub fn print_obstacle(input: &str, turn_policy: TurnPolicy, (x, y): (usize, usize), style: Style, sentinel: &SolverSentinel) -> Result<(), String> {
+    let map = NavigatableMap::new(input).with_turn_policy(turn_policy);
+    if x >= map.w || y >= map.h {
+        return Err(format!("({}, {}) is outside the {}x{} map", x, y, map.w, map.h));
+    }
+    if map.patroller.pos == (x, y) || map.tile_at(x, y) == Tile::Obstacle {
+        return Err(format!("({}, {}) is the guard's start or already an obstacle", x, y));
+    }
+    let loop_tiles = map.looping_obstacle_at(x, y).map_or_else(Vec::new, |looping| looping.tiles);
+    let mut blocked = map.with_obstacle(x, y);
+    while blocked.step() == HasFinished::Unfinished {}
+    let overlay = Overlay {
+        obstacles: vec![(x, y)],
+        loop_tiles,
+    };
+    sentinel_println!(Sentinel: sentinel, println!("{}", render(&blocked, style, &overlay)));
+    Ok(())
+}
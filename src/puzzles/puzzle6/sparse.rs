@@ -0,0 +1,231 @@
+extern crate alloc;
+use super::{HasFinished, MapError, Patroller, TurnPolicy};
+use crate::grid::Direction;
+use ahash::{AHashMap, AHashSet};
+use alloc::collections::BTreeMap;
+use core::str::FromStr;
+/// Map kept as the obstacle coordinates of every row and column, for maps far too large for a tile grid.
+///
+/// Walks jump from obstacle to obstacle with a binary search, so they only need memory for the obstacles and
+/// the turns taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SparseMap {
+    w: usize,
+    h: usize,
+    // y -> sorted x of every obstacle in the row
+    rows: AHashMap<usize, Vec<usize>>,
+    // x -> sorted y of every obstacle in the column
+    cols: AHashMap<usize, Vec<usize>>,
+    start: Patroller,
+    turn_policy: TurnPolicy,
+}
+impl FromStr for SparseMap {
+    type Err = MapError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut rows: AHashMap<usize, Vec<usize>> = AHashMap::new();
+        let mut cols: AHashMap<usize, Vec<usize>> = AHashMap::new();
+        let mut guards = Vec::new();
+        let (mut w, mut h) = (0, 0);
+        for (y, row) in input.trim_end().lines().map(str::trim).enumerate() {
+            if y == 0 {
+                w = row.len();
+            } else if row.len() != w {
+                return Err(MapError::RaggedRow {
+                    y,
+                    len: row.len(),
+                    expected: w,
+                });
+            }
+            h = y + 1;
+            for (x, byte) in row.bytes().enumerate() {
+                match byte {
+                    b'#' => {
+                        // rows fill up in order of x and columns in order of y, so both stay sorted
+                        rows.entry(y).or_default().push(x);
+                        cols.entry(x).or_default().push(y);
+                    }
+                    b'.' => {}
+                    _ => match Direction::from_arrow(byte) {
+                        Some(dir) => guards.push(Patroller {
+                            pos: (x, y),
+                            dir,
+                            odd_turns: false,
+                        }),
+                        None => return Err(MapError::InvalidTile { pos: (x, y), byte }),
+                    },
+                }
+            }
+        }
+        let start = match guards.len() {
+            0 => return Err(MapError::NoGuard),
+            1 => guards.pop().unwrap(),
+            _ => return Err(MapError::MultipleGuards(guards.into_iter().map(|guard| guard.pos).collect())),
+        };
+        Ok(Self {
+            w,
+            h,
+            rows,
+            cols,
+            start,
+            turn_policy: TurnPolicy::default(),
+        })
+    }
+}
+impl SparseMap {
+    #[inline]
+    #[must_use]
+    pub const fn with_turn_policy(mut self, turn_policy: TurnPolicy) -> Self {
+        self.turn_policy = turn_policy;
+        self
+    }
+    /// Where a patroller at `pos` facing `dir` stops, with `extra` treated as one more obstacle.
+    /// `None` if it walks off the map.
+    #[inline]
+    pub fn stop(&self, (x, y): (usize, usize), dir: Direction, extra: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        let ahead = match dir {
+            Direction::Up => self
+                .cols
+                .get(&x)
+                .and_then(|col| col[..col.partition_point(|&oy| oy < y)].last())
+                .map(|&oy| (x, oy)),
+            Direction::Down => self
+                .cols
+                .get(&x)
+                .and_then(|col| col.get(col.partition_point(|&oy| oy <= y)))
+                .map(|&oy| (x, oy)),
+            Direction::Left => self
+                .rows
+                .get(&y)
+                .and_then(|row| row[..row.partition_point(|&ox| ox < x)].last())
+                .map(|&ox| (ox, y)),
+            Direction::Right => self
+                .rows
+                .get(&y)
+                .and_then(|row| row.get(row.partition_point(|&ox| ox <= x)))
+                .map(|&ox| (ox, y)),
+            Direction::UpRight | Direction::DownRight | Direction::DownLeft | Direction::UpLeft => {
+                unreachable!("Guards only walk along rows and columns")
+            }
+        };
+        let obstacle = match (ahead, extra.filter(|&extra| dir.distance_to((x, y), extra).is_some())) {
+            (Some(ahead), Some(extra)) => Some(if dir.distance_to((x, y), extra) < dir.distance_to((x, y), ahead) {
+                extra
+            } else {
+                ahead
+            }),
+            (ahead, extra) => ahead.or(extra),
+        };
+        obstacle.map(|(ox, oy)| dir.reversed().offset(ox, oy).unwrap())
+    }
+    // last tile before walking off the map
+    fn edge(&self, (x, y): (usize, usize), dir: Direction) -> (usize, usize) {
+        match dir {
+            Direction::Up => (x, 0),
+            Direction::Down => (x, self.h - 1),
+            Direction::Left => (0, y),
+            Direction::Right => (self.w - 1, y),
+            Direction::UpRight | Direction::DownRight | Direction::DownLeft | Direction::UpLeft => {
+                unreachable!("Guards only walk along rows and columns")
+            }
+        }
+    }
+    /// Walks from `start` run by run, handing every straight run to `on_run` as the patroller at its beginning and
+    /// the tile it ends on.
+    #[inline]
+    pub fn walk_with<F>(&self, start: &Patroller, extra: Option<(usize, usize)>, mut on_run: F) -> HasFinished
+    where
+        F: FnMut(&Patroller, (usize, usize)),
+    {
+        let mut patroller = start.clone();
+        let mut turns = AHashSet::new();
+        loop {
+            let stop = self.stop(patroller.pos, patroller.dir, extra);
+            on_run(&patroller, stop.unwrap_or_else(|| self.edge(patroller.pos, patroller.dir)));
+            let Some(pos) = stop else {
+                return HasFinished::OutOfBounds;
+            };
+            patroller.pos = pos;
+            if !turns.insert(patroller.clone()) {
+                return HasFinished::LoopEntered;
+            }
+            self.turn_policy.turn(&mut patroller);
+        }
+    }
+    /// Number of distinct tiles the unobstructed walk covers.
+    ///
+    /// Runs are merged per row and per column, and the tiles where a horizontal and a vertical run cross are
+    /// subtracted once.
+    #[inline]
+    pub fn covered_tiles(&self) -> u64 {
+        let mut horizontal: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+        let mut vertical: AHashMap<usize, Vec<(usize, usize)>> = AHashMap::new();
+        self.walk_with(&self.start, None, |from, to| match from.dir {
+            Direction::Left | Direction::Right => {
+                horizontal.entry(to.1).or_default().push((from.pos.0.min(to.0), from.pos.0.max(to.0)));
+            }
+            Direction::Up | Direction::Down => {
+                vertical.entry(to.0).or_default().push((from.pos.1.min(to.1), from.pos.1.max(to.1)));
+            }
+            Direction::UpRight | Direction::DownRight | Direction::DownLeft | Direction::UpLeft => {
+                unreachable!("Guards only walk along rows and columns")
+            }
+        });
+        for runs in horizontal.values_mut().chain(vertical.values_mut()) {
+            merge(runs);
+        }
+        let length = |runs: &Vec<(usize, usize)>| runs.iter().map(|&(lo, hi)| (hi - lo + 1) as u64).sum::<u64>();
+        let mut covered = horizontal.values().map(length).sum::<u64>() + vertical.values().map(length).sum::<u64>();
+        for (&x, runs) in &vertical {
+            for &(lo, hi) in runs {
+                for row in horizontal.range(lo..=hi) {
+                    let index = row.1.partition_point(|&(_, end)| end < x);
+                    if row.1.get(index).is_some_and(|&(start, _)| start <= x) {
+                        covered -= 1;
+                    }
+                }
+            }
+        }
+        covered
+    }
+    /// Number of air tiles the unobstructed walk never reaches if it loops by itself, like
+    /// [`super::NavigatableMap::unreached_traps`]. They are counted rather than listed, there can be far too many.
+    #[inline]
+    pub fn unreached_traps(&self) -> usize {
+        if self.walk_with(&self.start, None, |_, _| {}) == HasFinished::OutOfBounds {
+            return 0;
+        }
+        let obstacles = self.rows.values().map(Vec::len).sum::<usize>();
+        self.w * self.h - obstacles - self.covered_tiles() as usize
+    }
+    /// Distinct tiles of the unobstructed walk in first-visit order with the patroller just before entering them,
+    /// like [`super::NavigatableMap::first_entries`].
+    #[inline]
+    pub fn first_entries(&self) -> Vec<((usize, usize), Patroller)> {
+        let mut entered = AHashSet::from([self.start.pos]);
+        let mut entries = Vec::new();
+        self.walk_with(&self.start, None, |from, to| {
+            let (x, y) = from.pos;
+            let mut before = from.clone();
+            for steps in 1..=from.dir.distance_to(from.pos, to).unwrap_or(0) {
+                let tile = from.dir.offset_times(x, y, steps).unwrap();
+                if entered.insert(tile) {
+                    entries.push((tile, before.clone()));
+                }
+                before.pos = tile;
+            }
+        });
+        entries
+    }
+}
+// sorts inclusive ranges and joins the overlapping ones
+fn merge(runs: &mut Vec<(usize, usize)>) {
+    runs.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(runs.len());
+    for &(lo, hi) in runs.iter() {
+        match merged.last_mut() {
+            Some(last) if lo <= last.1 => last.1 = last.1.max(hi),
+            _ => merged.push((lo, hi)),
+        }
+    }
+    *runs = merged;
+}
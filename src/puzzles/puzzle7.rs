@@ -1,46 +1,424 @@
+use crate::sentinel_println;
 use crate::solver::SolverSentinel;
+use ahash::AHashSet;
+use core::fmt;
+use core::mem;
+use core::str::FromStr;
 
+/// How a line of keepers is evaluated once the operators are placed between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EvaluationMode {
+    /// The puzzle rules: strictly left-to-right, `+` and `*` bind equally.
+    #[default]
+    LeftToRight,
+    /// Conventional precedence, `*` binds tighter than `+`.
+    Precedence,
+    /// Any parenthesization of the sequence, operand order preserved.
+    AnyParenthesization,
+}
+
+impl FromStr for EvaluationMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "ltr" | "left-to-right" => Self::LeftToRight,
+            "precedence" => Self::Precedence,
+            "parens" | "any-parenthesization" => Self::AnyParenthesization,
+            _ => return Err(format!("Invalid evaluation mode: {}", s)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct GoalKeeper {
-    goal: usize,
-    keepers: Vec<u16>,
+    goal: u64,
+    keepers: Vec<u64>,
+    // neither operator can shrink a value when every keeper is at least 1, which is what lets the searches throw
+    // away anything above the goal; a `0` keeper can multiply any value back down
+    prune: bool,
 }
 
-enum Operator {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
     Add,
-    Sub,
-    Div,
     Mul,
 }
 
+impl Operator {
+    pub const ALL: [Self; 2] = [Self::Add, Self::Mul];
+
+    #[inline]
+    pub const fn apply(self, lhs: u64, rhs: u64) -> Option<u64> {
+        match self {
+            Self::Add => lhs.checked_add(rhs),
+            Self::Mul => lhs.checked_mul(rhs),
+        }
+    }
+
+    #[inline]
+    pub const fn symbol(self) -> char {
+        match self {
+            Self::Add => '+',
+            Self::Mul => '*',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Num(u64),
+    BinOp(Box<Self>, Operator, Box<Self>),
+}
+
+impl Expr {
+    #[inline]
+    pub fn eval(&self) -> Option<u64> {
+        match *self {
+            Self::Num(n) => Some(n),
+            Self::BinOp(ref lhs, op, ref rhs) => op.apply(lhs.eval()?, rhs.eval()?),
+        }
+    }
+
+    /// Builds the tree the given mode would evaluate for a flat operator sequence.
+    #[inline]
+    pub fn from_flat(keepers: &[u64], ops: &[Operator], mode: EvaluationMode) -> Self {
+        assert!(keepers.len() == ops.len() + 1, "Need exactly one operator between each pair of keepers");
+        match mode {
+            EvaluationMode::LeftToRight | EvaluationMode::AnyParenthesization => {
+                ops.iter().zip(&keepers[1..]).fold(Self::Num(keepers[0]), |lhs, (&op, &rhs)| {
+                    Self::BinOp(Box::new(lhs), op, Box::new(Self::Num(rhs)))
+                })
+            }
+            EvaluationMode::Precedence => {
+                let mut sum: Option<Self> = None;
+                let mut product = Self::Num(keepers[0]);
+                for (&op, &rhs) in ops.iter().zip(&keepers[1..]) {
+                    match op {
+                        Operator::Mul => product = Self::BinOp(Box::new(product), Operator::Mul, Box::new(Self::Num(rhs))),
+                        Operator::Add => {
+                            let finished = mem::replace(&mut product, Self::Num(rhs));
+                            sum = Some(match sum {
+                                None => finished,
+                                Some(sum) => Self::BinOp(Box::new(sum), Operator::Add, Box::new(finished)),
+                            });
+                        }
+                    }
+                }
+                match sum {
+                    None => product,
+                    Some(sum) => Self::BinOp(Box::new(sum), Operator::Add, Box::new(product)),
+                }
+            }
+        }
+    }
+
+    /// Formats the expression so that reading it back under `mode` gives the same tree.
+    #[inline]
+    pub const fn display(&self, mode: EvaluationMode) -> ExprDisplay<'_> {
+        ExprDisplay { expr: self, mode }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, mode: EvaluationMode, parenthesize: bool) -> fmt::Result {
+        match *self {
+            Self::Num(n) => write!(f, "{}", n),
+            Self::BinOp(ref lhs, op, ref rhs) => {
+                let (lhs_parens, rhs_parens) = match mode {
+                    // left operand is always evaluated first, only a compound right operand needs grouping
+                    EvaluationMode::LeftToRight => (false, matches!(**rhs, Self::BinOp(..))),
+                    EvaluationMode::Precedence => (
+                        op == Operator::Mul && matches!(**lhs, Self::BinOp(_, Operator::Add, _)),
+                        matches!(**rhs, Self::BinOp(_, rhs_op, _) if op == Operator::Mul || rhs_op == Operator::Add),
+                    ),
+                    EvaluationMode::AnyParenthesization => (true, true),
+                };
+                if parenthesize {
+                    write!(f, "(")?;
+                }
+                lhs.write(f, mode, lhs_parens)?;
+                write!(f, " {} ", op.symbol())?;
+                rhs.write(f, mode, rhs_parens)?;
+                if parenthesize {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct ExprDisplay<'a> {
+    expr: &'a Expr,
+    mode: EvaluationMode,
+}
+
+impl fmt::Display for ExprDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expr.write(f, self.mode, false)
+    }
+}
+
 impl GoalKeeper {
     #[inline]
-    pub fn find_solution(&self) -> Vec<Operator> {
-        let mut solution = Vec::new();
+    pub fn parse(line: &str) -> Self {
+        let (goal, keepers) = line.split_once(':').unwrap();
+        let goal = goal.trim().parse::<u64>().unwrap();
+        let keepers = keepers.split_ascii_whitespace().map(|k| k.parse::<u64>().unwrap()).collect::<Vec<_>>();
+        assert!(!keepers.is_empty(), "Line has no keepers: {}", line);
+        let prune = !keepers.contains(&0);
+        Self { goal, keepers, prune }
+    }
+
+    #[inline]
+    pub fn find_solution(&self, mode: EvaluationMode) -> Option<Expr> {
+        match mode {
+            EvaluationMode::LeftToRight => {
+                let mut ops = Vec::with_capacity(self.keepers.len() - 1);
+                self.search_left_to_right(self.keepers[0], &mut ops)
+                    .then(|| Expr::from_flat(&self.keepers, &ops, mode))
+            }
+            EvaluationMode::Precedence => {
+                let mut ops = Vec::with_capacity(self.keepers.len() - 1);
+                self.search_precedence(0, self.keepers[0], &mut ops)
+                    .then(|| Expr::from_flat(&self.keepers, &ops, mode))
+            }
+            EvaluationMode::AnyParenthesization => self.search_any_parenthesization(),
+        }
+    }
+
+    fn search_left_to_right(&self, acc: u64, ops: &mut Vec<Operator>) -> bool {
+        let Some(&next) = self.keepers.get(ops.len() + 1) else {
+            return acc == self.goal;
+        };
+        for op in Operator::ALL {
+            let Some(acc) = op.apply(acc, next).filter(|&acc| !self.prune || acc <= self.goal) else {
+                continue;
+            };
+            ops.push(op);
+            if self.search_left_to_right(acc, ops) {
+                return true;
+            }
+            ops.pop();
+        }
+        false
+    }
+
+    // `sum` holds every finished product, `product` is the run of multiplications still open
+    fn search_precedence(&self, sum: u64, product: u64, ops: &mut Vec<Operator>) -> bool {
+        let Some(&next) = self.keepers.get(ops.len() + 1) else {
+            return sum.checked_add(product) == Some(self.goal);
+        };
+        for op in Operator::ALL {
+            let (sum, product) = match op {
+                Operator::Add => (sum.saturating_add(product), next),
+                Operator::Mul => (sum, product.saturating_mul(next)),
+            };
+            if self.prune && sum.saturating_add(product) > self.goal {
+                continue;
+            }
+            ops.push(op);
+            if self.search_precedence(sum, product, ops) {
+                return true;
+            }
+            ops.pop();
+        }
+        false
+    }
 
-        solution
+    fn search_any_parenthesization(&self) -> Option<Expr> {
+        let n = self.keepers.len();
+        // every split of an interval has a side at most half as long, so only those need their full value sets;
+        // without pruning a side can be multiplied by zero, and then any value of the other side will do
+        let short = if self.prune { (n / 2).max(1) } else { n };
+        let mut reachable: Vec<Vec<Vec<u64>>> = vec![vec![Vec::new(); n]; n];
+        for (i, &k) in self.keepers.iter().enumerate() {
+            reachable[i][i].push(k);
+        }
+        for len in 2..=short {
+            for i in 0..=n - len {
+                let j = i + len - 1;
+                let mut values = Vec::new();
+                for mid in i..j {
+                    for &lhs in &reachable[i][mid] {
+                        for op in Operator::ALL {
+                            // rhs is sorted, so the first value past the goal ends this row
+                            for &rhs in &reachable[mid + 1][j] {
+                                match op.apply(lhs, rhs) {
+                                    Some(value) if !self.prune || value <= self.goal => values.push(value),
+                                    _ => break,
+                                }
+                            }
+                        }
+                    }
+                }
+                values.sort_unstable();
+                values.dedup();
+                reachable[i][j] = values;
+            }
+        }
+        let mut search = ParenthesizationSearch {
+            keepers: &self.keepers,
+            reachable,
+            short,
+            dead_ends: AHashSet::new(),
+        };
+        search.find(0, n - 1, self.goal)
+    }
+}
+
+struct ParenthesizationSearch<'a> {
+    keepers: &'a [u64],
+    // sorted values of every interval no longer than `short`
+    reachable: Vec<Vec<Vec<u64>>>,
+    short: usize,
+    // (i, j, value) triples of long intervals already known to be unreachable
+    dead_ends: AHashSet<(usize, usize, u64)>,
+}
+
+impl ParenthesizationSearch<'_> {
+    fn find(&mut self, i: usize, j: usize, value: u64) -> Option<Expr> {
+        if i == j {
+            return (self.keepers[i] == value).then_some(Expr::Num(value));
+        }
+        let is_short = j - i < self.short;
+        if is_short && self.reachable[i][j].binary_search(&value).is_err() || self.dead_ends.contains(&(i, j, value)) {
+            return None;
+        }
+        for mid in i..j {
+            // enumerate the shorter side, ask the other side for whatever completes `value`
+            let (known, other) = if mid - i < j - mid {
+                ((i, mid), (mid + 1, j))
+            } else {
+                ((mid + 1, j), (i, mid))
+            };
+            for index in 0..self.reachable[known.0][known.1].len() {
+                let part = self.reachable[known.0][known.1][index];
+                // past `value` only a multiplication by zero can still get there
+                if part > value && value > 0 {
+                    break;
+                }
+                for op in Operator::ALL {
+                    let rest = match op {
+                        Operator::Add if part <= value => value - part,
+                        Operator::Mul if part == 0 && value == 0 => {
+                            let Some(&any) = self.reachable[other.0][other.1].first() else {
+                                continue;
+                            };
+                            any
+                        }
+                        Operator::Mul if part > 0 && value.is_multiple_of(part) => value / part,
+                        Operator::Add | Operator::Mul => continue,
+                    };
+                    let Some(other_expr) = self.find(other.0, other.1, rest) else { continue };
+                    let known_expr = self.find(known.0, known.1, part).unwrap();
+                    let (lhs, rhs) = if known.0 == i {
+                        (known_expr, other_expr)
+                    } else {
+                        (other_expr, known_expr)
+                    };
+                    return Some(Expr::BinOp(Box::new(lhs), op, Box::new(rhs)));
+                }
+            }
+        }
+        if !is_short {
+            self.dead_ends.insert((i, j, value));
+        }
+        None
     }
 }
 
 #[inline]
-pub fn solve_part_1(input: &str, _sentinel: &mut SolverSentinel) -> i32 {
-    let keepers = input
+pub fn solve_part_1(input: &str, sentinel: &SolverSentinel, mode: EvaluationMode, print_solutions: bool) -> u64 {
+    input
         .lines()
-        .map(|l| {
-            let mut iter = l.split(':');
-            let goal = iter.next().unwrap().parse::<usize>().unwrap();
-            let keepers = iter
-                .next()
-                .unwrap()
-                .split(',')
-                .map(|k| k.trim().parse::<u16>().unwrap())
-                .collect::<Vec<_>>();
-            GoalKeeper { goal, keepers }
+        .filter(|l| !l.trim().is_empty())
+        .map(GoalKeeper::parse)
+        .filter_map(|keeper| {
+            let solution = keeper.find_solution(mode)?;
+            debug_assert_eq!(solution.eval(), Some(keeper.goal), "Solution does not evaluate to its goal");
+            if print_solutions {
+                sentinel_println!(Sentinel: sentinel, println!("{} = {}", keeper.goal, solution.display(mode)));
+            }
+            Some(keeper.goal)
         })
-        .collect::<Vec<_>>();
-    0
+        .sum::<u64>()
 }
 
+#[allow(dead_code)] // unsolved
 #[inline]
-pub fn solve_part_2(input: &str, _sentinel: &mut SolverSentinel) -> i32 {
+pub fn solve_part_2(_input: &str, _sentinel: &mut SolverSentinel) -> i32 {
     todo!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::test_rng::Xorshift;
+
+    // every value the keepers can evaluate to under `mode`
+    fn reachable(keepers: &[u64], mode: EvaluationMode) -> AHashSet<u64> {
+        if mode == EvaluationMode::AnyParenthesization {
+            if keepers.len() == 1 {
+                return AHashSet::from([keepers[0]]);
+            }
+            let mut values = AHashSet::new();
+            for mid in 1..keepers.len() {
+                for &lhs in &reachable(&keepers[..mid], mode) {
+                    for &rhs in &reachable(&keepers[mid..], mode) {
+                        values.extend(Operator::ALL.iter().filter_map(|op| op.apply(lhs, rhs)));
+                    }
+                }
+            }
+            return values;
+        }
+        (0..1_u32 << (keepers.len() - 1))
+            .filter_map(|mask| {
+                let ops = (0..keepers.len() - 1)
+                    .map(|i| if mask & 1 << i == 0 { Operator::Add } else { Operator::Mul })
+                    .collect::<Vec<_>>();
+                Expr::from_flat(keepers, &ops, mode).eval()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn zero_keepers_disable_pruning() {
+        for (line, mode) in [
+            ("5: 3 0 5", EvaluationMode::LeftToRight),
+            ("7: 9 9 0 7", EvaluationMode::LeftToRight),
+            ("2: 5 0 2", EvaluationMode::Precedence),
+            ("4: 6 7 0 4", EvaluationMode::AnyParenthesization),
+            ("0: 8 9 0", EvaluationMode::AnyParenthesization),
+        ] {
+            let keeper = GoalKeeper::parse(line);
+            let solution = keeper.find_solution(mode);
+            assert_eq!(solution.and_then(|expr| expr.eval()), Some(keeper.goal), "{} under {:?}", line, mode);
+        }
+    }
+
+    #[test]
+    fn searches_match_brute_force() {
+        let mut rng = Xorshift::new();
+        for _ in 0..500 {
+            let keepers = (0..=rng.below(6)).map(|_| rng.below(6) as u64).collect::<Vec<_>>();
+            for mode in [
+                EvaluationMode::LeftToRight,
+                EvaluationMode::Precedence,
+                EvaluationMode::AnyParenthesization,
+            ] {
+                let values = reachable(&keepers, mode);
+                for goal in values.iter().copied().chain([rng.below(60) as u64]) {
+                    let keeper = GoalKeeper {
+                        goal,
+                        prune: !keepers.contains(&0),
+                        keepers: keepers.clone(),
+                    };
+                    let solution = keeper.find_solution(mode);
+                    assert_eq!(solution.is_some(), values.contains(&goal), "{} = {:?} under {:?}", goal, keepers, mode);
+                    if let Some(solution) = solution {
+                        assert_eq!(solution.eval(), Some(goal), "{} = {:?} under {:?}", goal, keepers, mode);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+/// A small xorshift generator so the randomized tests are reproducible without pulling in a crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xorshift(u64);
+
+impl Xorshift {
+    /// Starts from the same fixed seed every time, so a failing case can be replayed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// The next value in `0..bound`.
+    #[inline]
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
@@ -50,7 +50,7 @@ impl SolverSentinel {
                 match thread_msg {
                     ThreadMessage::Message(msg) => println!("{}", msg),
                     ThreadMessage::Close => break,
-                };
+                }
             }
         });
         let handle = Some(handle);